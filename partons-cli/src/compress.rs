@@ -0,0 +1,117 @@
+//! Compress replicas sets.
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, ValueEnum};
+
+use partons::compression::Compression;
+use partons::configs::Configs;
+use partons::sampling::{Basis, Sampling};
+use partons::set::Set;
+
+/// Flavor basis in which replicas are compared
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BasisArg {
+    Flavor,
+    Evolution,
+}
+
+impl From<BasisArg> for Basis {
+    fn from(basis: BasisArg) -> Self {
+        match basis {
+            BasisArg::Flavor => Basis::Flavor,
+            BasisArg::Evolution => Basis::Evolution,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct CompressArgs {
+    /// Name of the replicas set
+    set: String,
+    /// Number of replicas to keep
+    replicas: usize,
+    /// Name of the compressed set
+    #[arg(long)]
+    name: Option<String>,
+    /// Source of the set, by default all the configured ones are searched
+    #[arg(long)]
+    source: Option<String>,
+    /// Scales at which replicas are compared, by default the lowest of the set
+    #[arg(long, value_delimiter = ',')]
+    q: Vec<f64>,
+    /// Flavor basis in which replicas are compared
+    #[arg(long, value_enum, default_value_t = BasisArg::Flavor)]
+    basis: BasisArg,
+    /// Number of generations of the genetic minimization
+    #[arg(long, default_value_t = Compression::default().generations)]
+    generations: usize,
+    /// Seed of the random generator
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+impl CompressArgs {
+    /// Look the set up in the selected source, or in all the configured ones.
+    fn find(&self, configs: Configs) -> Result<Set> {
+        let path = configs.data_path()?;
+        let mut errors = Vec::new();
+        for mut source in configs.sources {
+            if self
+                .source
+                .as_ref()
+                .map_or(false, |name| source.name() != name)
+            {
+                continue;
+            }
+            source.register_cache(path.clone());
+            match source.find(&self.set) {
+                Ok(set) => return Ok(set),
+                Err(err) => errors.push(format!("{}: {err}", source.name())),
+            }
+        }
+
+        match &self.source {
+            Some(name) if errors.is_empty() => bail!("No source {name} configured"),
+            None if errors.is_empty() => bail!("No source configured"),
+            _ => bail!("Set {} not found:\n\t{}", self.set, errors.join("\n\t")),
+        }
+    }
+
+    pub(crate) fn run(self) -> Result<ExitCode> {
+        let mut set = self.find(Configs::load()?)?;
+
+        let info = set.info()?;
+        let pids = info
+            .flavors
+            .iter()
+            .map(|pid| i32::try_from(*pid))
+            .collect::<Result<_, _>>()?;
+        let qs = if self.q.is_empty() {
+            vec![info
+                .q_min
+                .ok_or_else(|| anyhow!("No scale available for sampling"))?]
+        } else {
+            self.q.clone()
+        };
+        let sampling = Sampling::new(
+            pids,
+            Sampling::default_x(),
+            qs.iter().map(|q| q * q).collect(),
+            &self.basis.into(),
+        )?;
+
+        let compression = Compression {
+            generations: self.generations,
+            seed: self.seed,
+            ..Compression::default()
+        };
+        let name = self
+            .name
+            .unwrap_or_else(|| format!("{}_compressed_{}", self.set, self.replicas));
+        let (compressed, erf) = set.compress(&sampling, self.replicas, &compression, &name)?;
+
+        println!("{}: error function {erf:.4}", compressed.name());
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -7,6 +7,7 @@ use clap::{Parser, Subcommand};
 mod macros;
 
 mod cache;
+mod compress;
 mod configs;
 mod list;
 
@@ -22,13 +23,14 @@ struct Cli {
 #[derive(Subcommand)]
 pub(crate) enum Command {
     Cache(cache::CacheArgs),
+    Compress(compress::CompressArgs),
     Configs(configs::ConfigsArgs),
     List(list::ListArgs),
 }
 
 impl Command {
     pub(crate) fn run(self) -> Result<ExitCode> {
-        run!(self; Cache, Configs, List)
+        run!(self; Cache, Compress, Configs, List)
     }
 }
 
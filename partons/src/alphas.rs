@@ -0,0 +1,266 @@
+//! Strong coupling
+//!
+//! The running of `\alpha_s` consistent with a set is determined by the set [`Info`], following
+//! the `AlphaS_Type` key: it can be interpolated from tabulated values (`ipol`), computed with an
+//! approximate analytic solution (`analytic`), or by numerically solving the renormalization
+//! group equation (`ode`).
+use std::f64::consts::PI;
+
+use anyhow::{anyhow, bail, Result};
+use ndarray::Array1;
+
+use crate::info::Info;
+use crate::quantity::Quantity;
+
+mod analytic;
+mod ode;
+
+pub use analytic::Analytic;
+pub use ode::Ode;
+
+/// Riemann zeta function in 3
+const ZETA3: f64 = 1.202_056_903_159_594_3;
+
+/// Default mass of the Z boson, in GeV
+pub(crate) const MZ: f64 = 91.1876;
+/// Default heavy quark masses (charm, bottom, top), in GeV
+pub(crate) const MASSES: [f64; 3] = [1.29, 4.19, 172.9];
+
+/// Strong coupling evaluator
+#[derive(Debug, Clone)]
+pub enum AlphaS {
+    /// Interpolation of the values tabulated in the set metadata, on the `AlphaS_Qs` scales
+    Ipol(Quantity),
+    /// Approximate analytic solution of the running
+    Analytic(Analytic),
+    /// Numerical solution of the running
+    Ode(Ode),
+}
+
+/// Coefficient of the beta function, at `loop_` loops and `nf` active flavors.
+///
+/// The normalization is such that:
+/// ```text
+/// d \alpha_s / d \log(\mu^2) = - \sum_i \beta_i \alpha_s^{i+2}
+/// ```
+pub(crate) fn beta(loop_: usize, nf: u8) -> f64 {
+    let nf = f64::from(nf);
+    match loop_ {
+        0 => (33. - 2. * nf) / (12. * PI),
+        1 => (153. - 19. * nf) / (24. * PI.powi(2)),
+        2 => (2857. - 5033. / 9. * nf + 325. / 27. * nf.powi(2)) / (128. * PI.powi(3)),
+        3 => {
+            (149753. / 6. + 3564. * ZETA3 - (1078361. / 162. + 6508. / 27. * ZETA3) * nf
+                + (50065. / 162. + 6472. / 81. * ZETA3) * nf.powi(2)
+                + 1093. / 729. * nf.powi(3))
+                / (256. * PI.powi(4))
+        }
+        _ => 0.,
+    }
+}
+
+/// Parameters of the running coupling
+#[derive(Debug, Clone)]
+pub struct Running {
+    /// Number of loops in the beta function, from 1 to 4
+    pub loops: usize,
+    /// Reference scale, squared
+    pub mu2_ref: f64,
+    /// Value of the coupling at the reference scale
+    pub alphas_ref: f64,
+    /// Heavy quark masses (charm, bottom, top)
+    pub masses: [f64; 3],
+    /// Maximum number of active flavors
+    pub nf_max: u8,
+}
+
+impl Running {
+    /// Number of active flavors at the scale `q2`.
+    ///
+    /// A heavy quark is active from its mass threshold on, included.
+    pub fn nf(&self, q2: f64) -> u8 {
+        let active = self.masses.iter().filter(|m| *m * *m <= q2).count() as u8;
+        (3 + active).min(self.nf_max)
+    }
+
+    /// Squared mass thresholds between `nf` and `nf + 1` active flavors.
+    pub(crate) fn threshold(&self, nf: u8) -> f64 {
+        self.masses[(nf - 3) as usize].powi(2)
+    }
+
+    fn from_info(info: &Info) -> Result<Self> {
+        // as in LHAPDF, the order of the set is the default
+        let order = info.alphas_order_qcd.unwrap_or(info.order.0);
+        let mz = info.mz.unwrap_or(MZ);
+        let alphas_ref = info
+            .alphas_mz
+            .ok_or_else(|| anyhow!("Missing field AlphaS_MZ"))?;
+        let mut masses = MASSES;
+        for (mass, value) in masses
+            .iter_mut()
+            .zip([info.m_charm, info.m_bottom, info.m_top])
+        {
+            if let Some(value) = value {
+                *mass = value;
+            }
+        }
+        let nf_max = u8::try_from(info.alphas_num_flavors.or(info.num_flavors).unwrap_or(5))?;
+
+        if order > 3 {
+            bail!("Running of alpha_s only available up to 4 loops");
+        }
+        if nf_max < 3 {
+            bail!("Running of alpha_s requires at least 3 flavors, {nf_max} given");
+        }
+
+        Ok(Self {
+            loops: order as usize + 1,
+            mu2_ref: mz * mz,
+            alphas_ref,
+            masses,
+            nf_max,
+        })
+    }
+}
+
+impl AlphaS {
+    /// Build the evaluator from the set metadata.
+    ///
+    /// The kind of evaluator is determined by the `AlphaS_Type` key. If missing, the tabulated
+    /// values are interpolated when available, otherwise the running is solved numerically.
+    pub fn from_info(info: &Info) -> Result<Self> {
+        let kind = info.alphas_type.as_deref().unwrap_or({
+            if info.alphas_vals.is_empty() {
+                "ode"
+            } else {
+                "ipol"
+            }
+        });
+
+        match kind {
+            "ipol" => {
+                let q2s = Array1::from_vec(info.alphas_qs.clone()).mapv(|q| q * q);
+                let values = Array1::from_vec(info.alphas_vals.clone());
+                Ok(Self::Ipol(Quantity::new(q2s, values)?))
+            }
+            "analytic" => {
+                let lambdas = [
+                    info.alphas_lambda3,
+                    info.alphas_lambda4,
+                    info.alphas_lambda5,
+                    info.alphas_lambda6,
+                ];
+                let running = Running::from_info(info)?;
+                if lambdas.iter().any(Option::is_some) {
+                    Ok(Self::Analytic(Analytic::with_lambdas(running, &lambdas)?))
+                } else {
+                    Ok(Self::Analytic(Analytic::new(running)?))
+                }
+            }
+            "ode" => Ok(Self::Ode(Ode::new(Running::from_info(info)?))),
+            _ => bail!("Unknown alpha_s evaluator '{kind}'"),
+        }
+    }
+
+    /// Value of `\alpha_s` at the scale `q2`.
+    ///
+    /// The scale is the square of the renormalization scale, in GeV.
+    pub fn alphas_q2(&self, q2: f64) -> Result<f64> {
+        match self {
+            Self::Ipol(ipol) => Ok(ipol.interp(q2)),
+            Self::Analytic(analytic) => analytic.alphas_q2(q2),
+            Self::Ode(ode) => ode.alphas_q2(q2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) fn running(loops: usize) -> Running {
+        Running {
+            loops,
+            mu2_ref: MZ * MZ,
+            alphas_ref: 0.118,
+            masses: MASSES,
+            nf_max: 5,
+        }
+    }
+
+    #[test]
+    fn ode_one_loop() {
+        // at one loop, and fixed flavors, the solution is exact
+        let ode = Ode::new(running(1));
+        for q in [10., 50., 91.1876, 200., 1000.] {
+            let exact = 0.118 / (1. + beta(0, 5) * 0.118 * (q * q / (MZ * MZ)).ln());
+            assert!((ode.alphas_q2(q * q).unwrap() - exact).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn analytic_vs_ode() {
+        for loops in 1..=4 {
+            let analytic = Analytic::new(running(loops)).unwrap();
+            let ode = Ode::new(running(loops));
+
+            assert!((analytic.alphas_q2(MZ * MZ).unwrap() - 0.118).abs() < 1e-10);
+            assert!((ode.alphas_q2(MZ * MZ).unwrap() - 0.118).abs() < 1e-12);
+            // the truncated expansion deteriorates at low scales
+            for (q, tolerance) in [
+                (1.5, 6e-2),
+                (2., 4e-2),
+                (5., 1e-2),
+                (30., 1e-3),
+                (150., 1e-4),
+            ] {
+                let (a, o) = (
+                    analytic.alphas_q2(q * q).unwrap(),
+                    ode.alphas_q2(q * q).unwrap(),
+                );
+                assert!(
+                    (a / o - 1.).abs() < tolerance,
+                    "{loops} loops, Q = {q}: {a} vs {o}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn flavors() {
+        let running = running(3);
+        assert_eq!(running.nf(1.), 3);
+        assert_eq!(running.nf(4.19 * 4.19), 5);
+        assert_eq!(running.nf(1e6), 5);
+
+        // the coupling may run with fewer flavors than the set
+        let info = Info {
+            alphas_mz: Some(0.118),
+            num_flavors: Some(5),
+            alphas_num_flavors: Some(4),
+            ..Info::default()
+        };
+        assert_eq!(Running::from_info(&info).unwrap().nf(1e6), 4);
+        let info = Info {
+            alphas_num_flavors: Some(2),
+            ..info
+        };
+        assert!(Running::from_info(&info).is_err());
+    }
+
+    #[test]
+    fn order() {
+        // the order of the set is the default
+        let info = Info {
+            alphas_mz: Some(0.118),
+            order: (2, 0),
+            ..Info::default()
+        };
+        assert_eq!(Running::from_info(&info).unwrap().loops, 3);
+        let info = Info {
+            alphas_order_qcd: Some(1),
+            ..info
+        };
+        assert_eq!(Running::from_info(&info).unwrap().loops, 2);
+    }
+}
@@ -0,0 +1,131 @@
+//! Approximate analytic solution of the running
+use anyhow::{anyhow, bail, Result};
+
+use super::{beta, Running};
+
+/// Number of bisections to determine `Lambda`
+const BISECTIONS: usize = 200;
+
+/// Strong coupling from the approximate analytic solution of the running
+///
+/// The solution is the expansion in inverse powers of `log(mu2 / Lambda^2)` of the PDG review,
+/// with a different `Lambda` for each number of active flavors. Unless provided, the values of
+/// `Lambda` are determined by the reference value of the coupling, and continuity across the
+/// thresholds.
+#[derive(Debug, Clone)]
+pub struct Analytic {
+    running: Running,
+    /// Values of `Lambda`, for 3 to 6 active flavors
+    lambdas: [Option<f64>; 4],
+}
+
+/// Expansion of the coupling at `loops` loops, with `nf` active flavors.
+fn expansion(q2: f64, lambda: f64, loops: usize, nf: u8) -> f64 {
+    let t = (q2 / (lambda * lambda)).ln();
+    let lt = t.ln();
+    let b0 = beta(0, nf);
+    let (b1, b2, b3) = (beta(1, nf), beta(2, nf), beta(3, nf));
+
+    let mut sum = 1.;
+    if loops > 1 {
+        sum -= b1 / b0.powi(2) * lt / t;
+    }
+    if loops > 2 {
+        sum += (b1.powi(2) * (lt.powi(2) - lt - 1.) + b0 * b2) / (b0.powi(4) * t.powi(2));
+    }
+    if loops > 3 {
+        sum -= (b1.powi(3) * (lt.powi(3) - 2.5 * lt.powi(2) - 2. * lt + 0.5)
+            + 3. * b0 * b1 * b2 * lt
+            - 0.5 * b0.powi(2) * b3)
+            / (b0.powi(6) * t.powi(3));
+    }
+
+    sum / (b0 * t)
+}
+
+impl Analytic {
+    /// Build the evaluator from the `running` parameters, determining `Lambda` from the
+    /// reference value of the coupling.
+    pub fn new(running: Running) -> Result<Self> {
+        let loops = running.loops;
+        let nf_ref = running.nf(running.mu2_ref);
+        let mut lambdas = [None; 4];
+        let index = |nf: u8| (nf - 3) as usize;
+
+        lambdas[index(nf_ref)] = Some(solve(running.mu2_ref, running.alphas_ref, loops, nf_ref)?);
+
+        // impose continuity, going down and up from the reference
+        for nf in (3..nf_ref).rev() {
+            let threshold = running.threshold(nf);
+            let above = lambdas[index(nf + 1)].unwrap_or_default();
+            let alphas = expansion(threshold, above, loops, nf + 1);
+            lambdas[index(nf)] = Some(solve(threshold, alphas, loops, nf)?);
+        }
+        for nf in nf_ref + 1..=running.nf_max.min(6) {
+            let threshold = running.threshold(nf - 1);
+            let below = lambdas[index(nf - 1)].unwrap_or_default();
+            let alphas = expansion(threshold, below, loops, nf - 1);
+            lambdas[index(nf)] = Some(solve(threshold, alphas, loops, nf)?);
+        }
+
+        Ok(Self { running, lambdas })
+    }
+
+    /// Build the evaluator from the `running` parameters and the given values of `Lambda`, for
+    /// 3 to 6 active flavors.
+    pub fn with_lambdas(running: Running, lambdas: &[Option<f64>]) -> Result<Self> {
+        if lambdas.len() != 4 {
+            bail!(
+                "Lambda required for 3 to 6 flavors, {} given",
+                lambdas.len()
+            );
+        }
+
+        let mut values = [None; 4];
+        values.copy_from_slice(lambdas);
+        Ok(Self {
+            running,
+            lambdas: values,
+        })
+    }
+
+    /// Value of `\alpha_s` at the scale `q2`.
+    pub fn alphas_q2(&self, q2: f64) -> Result<f64> {
+        let nf = self.running.nf(q2);
+        let lambda = self
+            .lambdas
+            .get(nf.saturating_sub(3) as usize)
+            .copied()
+            .flatten()
+            .ok_or_else(|| anyhow!("Lambda not available for {nf} flavors"))?;
+
+        if q2 <= lambda * lambda {
+            bail!("Scale mu2 = {q2} below Lambda^2 = {}", lambda * lambda);
+        }
+        Ok(expansion(q2, lambda, self.running.loops, nf))
+    }
+}
+
+/// Determine `Lambda` such that the coupling is `alphas` at the scale `q2`.
+fn solve(q2: f64, alphas: f64, loops: usize, nf: u8) -> Result<f64> {
+    // bisect in log(Lambda), keeping far enough from the Landau pole
+    let (mut low, mut high) = (1e-6_f64.ln(), q2.ln() / 2. - 2_f64.ln());
+    let residual = |log: f64| expansion(q2, log.exp(), loops, nf) - alphas;
+    if residual(low) > 0. || residual(high) < 0. {
+        bail!("Unable to determine Lambda for alpha_s = {alphas} at mu2 = {q2}");
+    }
+
+    for _ in 0..BISECTIONS {
+        let mid = (low + high) / 2.;
+        if residual(mid) < 0. {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-15 {
+            break;
+        }
+    }
+
+    Ok(((low + high) / 2.).exp())
+}
@@ -0,0 +1,95 @@
+//! Numerical solution of the running
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+use super::{beta, Running};
+
+/// Maximum step in `log(mu2)` for the integration
+const STEP: f64 = 0.02;
+
+/// Strong coupling from the numerical solution of the renormalization group equation
+///
+/// The equation is integrated with a fourth-order Runge-Kutta, from the reference scale to the
+/// requested one, decoupling the heavy flavors on their mass thresholds.
+#[derive(Debug, Clone)]
+pub struct Ode {
+    running: Running,
+}
+
+impl Ode {
+    /// Build the evaluator from the `running` parameters.
+    pub fn new(running: Running) -> Self {
+        Self { running }
+    }
+
+    /// Derivative of the coupling in `log(mu2)`.
+    fn derivative(&self, alphas: f64, nf: u8) -> f64 {
+        -(0..self.running.loops)
+            .map(|i| beta(i, nf) * alphas.powi(i as i32 + 2))
+            .sum::<f64>()
+    }
+
+    /// Evolve `alphas` from `from` to `to`, at fixed flavors.
+    fn evolve(&self, mut alphas: f64, from: f64, to: f64, nf: u8) -> Result<f64> {
+        let span = (to / from).ln();
+        let steps = (span.abs() / STEP).ceil().max(1.);
+        let h = span / steps;
+
+        for _ in 0..steps as usize {
+            let k1 = self.derivative(alphas, nf);
+            let k2 = self.derivative(alphas + h / 2. * k1, nf);
+            let k3 = self.derivative(alphas + h / 2. * k2, nf);
+            let k4 = self.derivative(alphas + h * k3, nf);
+            alphas += h / 6. * (k1 + 2. * k2 + 2. * k3 + k4);
+
+            if !alphas.is_finite() || alphas <= 0. {
+                bail!("Running of alpha_s diverged, below mu2 = {to}");
+            }
+        }
+
+        Ok(alphas)
+    }
+
+    /// Decouple a heavy flavor, from `nf + 1` to `nf` flavors if `down`, or the opposite.
+    ///
+    /// Matching is performed at the scale of the quark mass, where it is trivial up to two loops.
+    fn matching(&self, alphas: f64, down: bool) -> f64 {
+        if self.running.loops < 3 {
+            return alphas;
+        }
+
+        let correction = 11. / 72. * (alphas / PI).powi(2);
+        if down {
+            alphas * (1. + correction)
+        } else {
+            alphas * (1. - correction)
+        }
+    }
+
+    /// Value of `\alpha_s` at the scale `q2`.
+    pub fn alphas_q2(&self, q2: f64) -> Result<f64> {
+        if q2 <= 0. {
+            bail!("Scale has to be positive, {q2} given");
+        }
+
+        let running = &self.running;
+        let (mut mu2, mut alphas) = (running.mu2_ref, running.alphas_ref);
+        let mut nf = running.nf(mu2);
+        let target = running.nf(q2);
+
+        // cross the thresholds, one at a time
+        while nf != target {
+            let (threshold, next) = if target > nf {
+                (running.threshold(nf), nf + 1)
+            } else {
+                (running.threshold(nf - 1), nf - 1)
+            };
+            alphas = self.evolve(alphas, mu2, threshold, nf)?;
+            alphas = self.matching(alphas, next < nf);
+            (mu2, nf) = (threshold, next);
+        }
+
+        self.evolve(alphas, mu2, q2, nf)
+    }
+}
@@ -2,16 +2,45 @@
 use std::collections::HashMap;
 
 use anyhow::{anyhow, Result};
-use ndarray::{Array1, Array3};
+use ndarray::{Array1, Array3, Axis};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::extrapolation::{self, Extrapolator};
+use crate::interpolation::{Interpolator, Knots};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "RawBlock")]
 pub struct Block {
     pub pids: Array1<i32>,
     pub xgrid: Array1<f64>,
     pub mu2grid: Array1<f64>,
     pub(crate) values: Array3<f64>,
     pid_map: HashMap<i32, usize>,
+    // logarithms of the knots, cached for the interpolation
+    #[serde(skip)]
+    logxgrid: Array1<f64>,
+    #[serde(skip)]
+    logmu2grid: Array1<f64>,
+}
+
+/// Serialized content of a [`Block`]
+///
+/// Only used to rebuild the derived fields on deserialization.
+#[derive(Deserialize)]
+struct RawBlock {
+    pids: Array1<i32>,
+    xgrid: Array1<f64>,
+    mu2grid: Array1<f64>,
+    values: Array3<f64>,
+    // part of the serialized layout, but always recomputed
+    #[allow(dead_code)]
+    pid_map: HashMap<i32, usize>,
+}
+
+impl From<RawBlock> for Block {
+    fn from(raw: RawBlock) -> Self {
+        Self::new(raw.pids, raw.xgrid, raw.mu2grid, raw.values)
+    }
 }
 
 impl Block {
@@ -24,6 +53,8 @@ impl Block {
         assert_eq!(values.shape(), &[pids.len(), xgrid.len(), mu2grid.len()]);
 
         let pid_map = pids.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+        let logxgrid = xgrid.mapv(f64::ln);
+        let logmu2grid = mu2grid.mapv(f64::ln);
 
         Self {
             pids,
@@ -31,38 +62,94 @@ impl Block {
             mu2grid,
             values,
             pid_map,
+            logxgrid,
+            logmu2grid,
         }
     }
 
     pub(crate) fn pid_index(&self, pid: i32) -> Result<usize> {
         self.pid_map
             .get(&pid)
-            .ok_or(anyhow!("PID '{}' not found", pid))
+            .ok_or_else(|| anyhow!("PID '{}' not found", pid))
             .map(|idx| *idx)
     }
 
-    // TODO: delegate interpolation to ndinterp
-    pub(crate) fn interp(&self, pid: i32, x: f64, mu2: f64) -> Result<f64> {
+    /// Interpolate the grid values for `pid` at the point `(x, mu2)`.
+    ///
+    /// Points outside the grid are delegated to the `extrapolator`.
+    pub(crate) fn interp(
+        &self,
+        pid: i32,
+        x: f64,
+        mu2: f64,
+        interpolator: &Interpolator,
+        extrapolator: &Extrapolator,
+    ) -> Result<f64> {
         // determine interpolation slice
-        let slice = self.pid_index(pid)?;
-
-        let idx = self
-            .xgrid
-            .iter()
-            .enumerate()
-            .filter(|(_, y)| y > &&x)
-            .next()
-            .unwrap()
-            .0;
-        let idmu = self
-            .mu2grid
-            .iter()
-            .enumerate()
-            .filter(|(_, nu2)| nu2 > &&mu2)
-            .next()
-            .unwrap()
-            .0;
-
-        return Ok(self.values[[slice, idx, idmu]]);
+        let slice = self.values.index_axis(Axis(0), self.pid_index(pid)?);
+        let xs = Knots {
+            knots: &self.xgrid,
+            logs: &self.logxgrid,
+        };
+        let mu2s = Knots {
+            knots: &self.mu2grid,
+            logs: &self.logmu2grid,
+        };
+        let interpolate = |x, mu2| interpolator.interpolate(&slice, xs, mu2s, x, mu2);
+
+        if extrapolation::contains(&self.xgrid, x) && extrapolation::contains(&self.mu2grid, mu2) {
+            interpolate(x, mu2)
+        } else {
+            extrapolator.extrapolate(interpolate, &self.xgrid, &self.mu2grid, x, mu2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::{array, Array};
+
+    fn block(f: impl Fn(f64, f64) -> f64, nmu2: usize) -> Block {
+        let xgrid = Array::geomspace(1e-5, 1., 30).unwrap();
+        let mu2grid = Array::geomspace(2., 1e5, nmu2).unwrap();
+        let values = Array3::from_shape_fn((1, xgrid.len(), mu2grid.len()), |(_, i, j)| {
+            f(xgrid[i], mu2grid[j])
+        });
+        Block::new(array![21], xgrid, mu2grid, values)
+    }
+
+    #[test]
+    fn knots() {
+        let f = |x: f64, mu2: f64| x.powf(0.3) * (1. - x).powi(3) * mu2.ln();
+        let block = block(f, 20);
+        let (interpolator, extrapolator) = (Interpolator::default(), Extrapolator::default());
+
+        for x in block.xgrid.iter() {
+            for mu2 in block.mu2grid.iter() {
+                let value = block
+                    .interp(21, *x, *mu2, &interpolator, &extrapolator)
+                    .unwrap();
+                assert!((value - f(*x, *mu2)).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn outside() {
+        let block = block(|_, _| 1., 10);
+        let interpolator = Interpolator::default();
+        let interp =
+            |pid, x, mu2, extrapolator| block.interp(pid, x, mu2, &interpolator, &extrapolator);
+
+        assert!(interp(21, 1e-6, 10., Extrapolator::Error).is_err());
+        assert!(interp(21, 0.1, 1e6, Extrapolator::Error).is_err());
+        assert_eq!(interp(21, 0.1, 1e6, Extrapolator::Nearest).unwrap(), 1.);
+        assert_eq!(
+            interp(21, 1e-6, 10., Extrapolator::Continuation).unwrap(),
+            1.
+        );
+        assert!(interp(1, 0.1, 10., Extrapolator::Nearest).is_err());
     }
 }
@@ -0,0 +1,258 @@
+//! Build sets from functions
+//!
+//! Members are generated by sampling a function on chosen grids, and then assembled in a set,
+//! together with its metadata. This is mainly meant for toy and closure-test distributions.
+use anyhow::{bail, Result};
+use ndarray::{Array1, Array3};
+use thiserror::Error;
+
+use crate::block::Block;
+use crate::data::source::Source;
+use crate::info::Info;
+use crate::member::{Member, Metadata, PDF_TYPE_KEY};
+use crate::set::Set;
+use crate::uncertainty::ErrorType;
+
+/// Error building a set
+#[derive(Error, Debug)]
+pub enum BuildError {
+    /// A grid has too few knots
+    #[error("The {0} grid requires at least two knots")]
+    TooFewKnots(&'static str),
+    /// Knots are not strictly increasing, or out of their domain
+    #[error("The {0} knots have to be positive and strictly increasing")]
+    InvalidKnots(&'static str),
+    /// Momentum fractions beyond one
+    #[error("Momentum fractions cannot exceed one")]
+    InvalidMomentumFraction,
+    /// No flavor in a block
+    #[error("Each block requires at least one flavor")]
+    NoFlavor,
+    /// Nothing to build
+    #[error("At least one {0} is required")]
+    Empty(&'static str),
+}
+
+fn knots(name: &'static str, knots: &[f64]) -> Result<Array1<f64>, BuildError> {
+    if knots.len() < 2 {
+        return Err(BuildError::TooFewKnots(name));
+    }
+    if knots[0] <= 0. || knots.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(BuildError::InvalidKnots(name));
+    }
+    Ok(Array1::from_vec(knots.to_vec()))
+}
+
+/// Builder of a [`Member`]
+///
+/// Blocks are defined by their flavors and knots, and are filled by sampling a function on all
+/// their points.
+///
+/// ```
+/// # use partons::builder::MemberBuilder;
+/// # use anyhow::Result;
+/// #
+/// # fn main() -> Result<()> {
+///       let xs = [1e-4, 1e-3, 1e-2, 0.1, 0.5, 1.];
+///       let member = MemberBuilder::new()
+///           .block(vec![21, 1], &xs, &[2., 10., 25.])
+///           .block(vec![21, 1, 4], &xs, &[25., 100., 1e4])
+///           .build(|pid, x, mu2| x.powf(0.5) * (1. - x).powi(3) * mu2.ln() * f64::from(pid))?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemberBuilder {
+    metadata: Metadata,
+    blocks: Vec<(Vec<i32>, Vec<f64>, Vec<f64>)>,
+}
+
+impl MemberBuilder {
+    /// Start an empty member.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a metadata entry, e.g. the interpolation strategy.
+    pub fn metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Add a block for the given `pids`, sampled on the `x` and `mu2` knots.
+    ///
+    /// Contiguous blocks should share their boundary scale, usually a heavy quark threshold.
+    pub fn block(mut self, pids: Vec<i32>, x: &[f64], mu2: &[f64]) -> Self {
+        self.blocks.push((pids, x.to_vec(), mu2.to_vec()));
+        self
+    }
+
+    /// Sample `f(pid, x, mu2)` on all the blocks points.
+    pub fn build(self, mut f: impl FnMut(i32, f64, f64) -> f64) -> Result<Member> {
+        if self.blocks.is_empty() {
+            bail!(BuildError::Empty("block"));
+        }
+
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        for (pids, x, mu2) in self.blocks {
+            if pids.is_empty() {
+                bail!(BuildError::NoFlavor);
+            }
+            let xgrid = knots("x", &x)?;
+            if xgrid[xgrid.len() - 1] > 1. {
+                bail!(BuildError::InvalidMomentumFraction);
+            }
+            let mu2grid = knots("mu2", &mu2)?;
+
+            let values =
+                Array3::from_shape_fn((pids.len(), xgrid.len(), mu2grid.len()), |(p, i, j)| {
+                    f(pids[p], xgrid[i], mu2grid[j])
+                });
+            blocks.push(Block::new(Array1::from_vec(pids), xgrid, mu2grid, values));
+        }
+        // members rely on blocks sorted by scale, to select them
+        blocks.sort_by(|a, b| a.mu2grid[0].total_cmp(&b.mu2grid[0]));
+
+        Ok(Member {
+            metadata: self.metadata,
+            blocks,
+            interpolator: None,
+            extrapolator: None,
+        })
+    }
+}
+
+/// Builder of a [`Set`]
+///
+/// The members are collected in order, the first one being the central member, and stored
+/// together with the `info` in the cache of a source, e.g. a [`Source::local`] one.
+#[derive(Debug, Clone)]
+pub struct SetBuilder {
+    name: String,
+    info: Info,
+    members: Vec<Member>,
+}
+
+impl SetBuilder {
+    /// Start a set with the given `name` and metadata.
+    pub fn new(name: &str, info: Info) -> Self {
+        Self {
+            name: name.to_owned(),
+            info,
+            members: Vec::new(),
+        }
+    }
+
+    /// Append a member.
+    pub fn member(mut self, member: Member) -> Self {
+        self.members.push(member);
+        self
+    }
+
+    /// Store the set in the `source` cache.
+    ///
+    /// The number of members is updated in the metadata, and flavors and grid limits are filled
+    /// from the central member, if missing. Members without a `PdfType` are marked according to
+    /// their position and the set error type.
+    pub fn build(mut self, source: &Source) -> Result<Set> {
+        let Some(central) = self.members.first() else {
+            bail!(BuildError::Empty("member"));
+        };
+        self.info
+            .complete(central, u32::try_from(self.members.len())?);
+
+        let error_type = self
+            .info
+            .error_type
+            .as_deref()
+            .map(str::parse)
+            .transpose()?;
+        let variation = match error_type {
+            Some(ErrorType::Replicas) => "replica",
+            _ => "error",
+        };
+        for (num, member) in self.members.iter_mut().enumerate() {
+            member
+                .metadata
+                .entry(PDF_TYPE_KEY.to_owned())
+                .or_insert_with(|| if num == 0 { "central" } else { variation }.to_owned());
+        }
+
+        source.store(&self.name, self.info, &self.members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::array;
+
+    fn f(pid: i32, x: f64, mu2: f64) -> f64 {
+        f64::from(pid) * x.powf(0.3) * (1. - x).powi(2) * mu2.ln()
+    }
+
+    #[test]
+    fn member() {
+        let x = [1e-5, 1e-3, 0.1, 0.3, 0.6, 1.];
+        let member = MemberBuilder::new()
+            .metadata("Interpolator", "loglinear")
+            .block(vec![1, 2], &x, &[25., 50., 100.])
+            .block(vec![1, 2], &x, &[2., 10., 25.])
+            .build(f)
+            .unwrap();
+
+        assert_eq!(member.metadata["Interpolator"], "loglinear");
+        // blocks are sorted
+        assert_eq!(member.blocks[0].mu2grid[0], 2.);
+        let values = member
+            .evaluate(&array![1, 2], &array![0.1], &array![10.])
+            .unwrap();
+        assert!((values[0] - f(1, 0.1, 10.)).abs() < 1e-12);
+        assert!((values[1] - f(2, 0.1, 10.)).abs() < 1e-12);
+
+        let invalid = |x: &[f64], mu2: &[f64]| {
+            MemberBuilder::new()
+                .block(vec![21], x, mu2)
+                .build(f)
+                .is_err()
+        };
+        assert!(invalid(&[0.1, 0.01], &[2., 4.]));
+        assert!(invalid(&[0.1, 2.], &[2., 4.]));
+        assert!(invalid(&[0.1, 0.2], &[4.]));
+        assert!(MemberBuilder::new().build(f).is_err());
+    }
+
+    #[test]
+    fn set() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = Source::local("builder-test");
+        source.register_cache(dir.path().to_owned());
+
+        let member = |scale: f64| {
+            MemberBuilder::new()
+                .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+                .build(|_, x, _| scale * (1. - x))
+                .unwrap()
+        };
+        let info = Info {
+            description: "Toy".to_owned(),
+            error_type: Some("replicas".to_owned()),
+            ..Info::default()
+        };
+        let mut set = SetBuilder::new("toy", info)
+            .member(member(1.))
+            .member(member(2.))
+            .build(&source)
+            .unwrap();
+
+        let info = set.info().unwrap();
+        assert_eq!(info.num_members, Some(2));
+        assert_eq!(info.flavors, vec![21]);
+        assert_eq!((info.x_min, info.q_max), (Some(1e-3), Some(10.)));
+        // members are reloaded from the cache
+        let replica = set.member(1).unwrap();
+        assert_eq!(replica.metadata[PDF_TYPE_KEY], "replica");
+        assert_eq!(replica.blocks[0].values[(0, 0, 0)], 2. * (1. - 1e-3));
+    }
+}
@@ -0,0 +1,157 @@
+//! Combination of Monte Carlo sets
+//!
+//! Replicas are drawn from each of the input sets, in chosen proportions, and merged in a single
+//! set, as done for PDF4LHC (arXiv:1510.03865). Since the inputs may have different knots, all
+//! the replicas are resampled on the grids of the first set.
+use anyhow::{bail, Result};
+use rand::seq::index::sample;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::block::Block;
+use crate::info::{Info, PID};
+use crate::set::Set;
+use crate::uncertainty::ErrorType;
+
+/// Metadata of the combination of sets with the given `infos` and `names`.
+///
+/// The first set provides the defaults, while the grid limits and flavors are taken from the
+/// common `blocks`. Extra fields are merged, with the earlier sets taking precedence.
+pub(crate) fn merge(infos: &[&Info], names: &[String], blocks: &[Block]) -> Info {
+    let mut info = infos[0].clone();
+
+    info.description = format!("Combination of {}.", names.join(", "));
+    info.authors = infos
+        .iter()
+        .map(|i| i.authors.as_str())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let references: Vec<_> = infos.iter().filter_map(|i| i.reference.clone()).collect();
+    info.reference = (!references.is_empty()).then(|| references.join("; "));
+    info.year = infos.iter().filter_map(|i| i.year).max();
+
+    let first = blocks.first();
+    let last = blocks.last();
+    info.flavors = first.map_or_else(Vec::new, |b| b.pids.iter().map(|p| PID::from(*p)).collect());
+    info.x_min = first.and_then(|b| b.xgrid.first().copied());
+    info.x_max = first.and_then(|b| b.xgrid.last().copied());
+    info.q_min = first.and_then(|b| b.mu2grid.first().map(|mu2| mu2.sqrt()));
+    info.q_max = last.and_then(|b| b.mu2grid.last().map(|mu2| mu2.sqrt()));
+
+    for other in &infos[1..] {
+        for (key, value) in &other.more_members {
+            info.more_members
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    info
+}
+
+impl Set {
+    /// Combine the replicas of `sets` in a new set, saved with the given `name`.
+    ///
+    /// The number of `replicas` drawn from each set is given in the same order, and the random
+    /// selection is fully determined by the `seed`. All the replicas are resampled on the grids
+    /// of the first set, and the central member of the new set is their average.
+    /// The result can be further exported to LHAPDF with [`Set::export`].
+    pub fn combine(sets: &mut [Set], replicas: &[usize], seed: u64, name: &str) -> Result<Set> {
+        if sets.is_empty() || sets.len() != replicas.len() {
+            bail!("The number of replicas has to be given for each set");
+        }
+        let blocks = sets[0].member(0)?.blocks.clone();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        let mut members = Vec::with_capacity(replicas.iter().sum::<usize>());
+        for (set, drawn) in sets.iter_mut().zip(replicas) {
+            let error_info = set.error_info()?;
+            if error_info.pdf != ErrorType::Replicas {
+                bail!("Only replicas sets can be combined, {} is not", set.name());
+            }
+            let available = error_info.pdf_members();
+            if *drawn > available.len() {
+                bail!(
+                    "{drawn} replicas requested, but {} has only {}",
+                    set.name(),
+                    available.len()
+                );
+            }
+
+            let metadata = set.mapping()?.clone();
+            let mut indices = sample(&mut rng, available.len(), *drawn).into_vec();
+            indices.sort_unstable();
+            for index in indices {
+                let num = u32::try_from(available.start + index)?;
+                members.push(set.member(num)?.resample(&blocks, &metadata)?);
+            }
+        }
+        if members.is_empty() {
+            bail!("At least one replica is required");
+        }
+
+        let names: Vec<_> = sets.iter().map(Set::name).collect();
+        for set in sets.iter_mut() {
+            set.info()?;
+        }
+        let infos: Vec<_> = sets.iter().filter_map(|set| set.info.as_ref()).collect();
+        let info = merge(&infos, &names, &blocks);
+
+        sets[0].store_replicas(name, info, members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::{array, Array3};
+
+    fn info(yaml: &str) -> Info {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn metadata() {
+        let a = info(
+            "
+description: A
+authors: Somebody
+year: 2021
+order: [2, 0]
+error_type: replicas
+more_members: {Interpolator: logcubic}
+",
+        );
+        let b = info(
+            "
+description: B
+authors: Somebody else
+reference: arXiv:0000.00000
+year: 2023
+order: [2, 0]
+more_members: {Interpolator: linear, Extra: 1}
+",
+        );
+        let block = Block::new(
+            array![21, 1],
+            array![1e-5, 0.1, 1.],
+            array![4., 100.],
+            Array3::zeros((2, 3, 2)),
+        );
+
+        let names = vec!["a".to_owned(), "b".to_owned()];
+        let merged = merge(&[&a, &b], &names, &[block]);
+        assert_eq!(merged.description, "Combination of a, b.");
+        assert_eq!(merged.authors, "Somebody; Somebody else");
+        assert_eq!(merged.reference.as_deref(), Some("arXiv:0000.00000"));
+        assert_eq!(merged.year, Some(2023));
+        assert_eq!(merged.flavors, vec![21, 1]);
+        assert_eq!((merged.x_min, merged.q_max), (Some(1e-5), Some(10.)));
+        assert_eq!(
+            merged.more_members["Interpolator"],
+            serde_yaml::Value::from("logcubic")
+        );
+        assert_eq!(merged.more_members.len(), 2);
+    }
+}
@@ -0,0 +1,337 @@
+//! Compression of Monte Carlo replicas
+//!
+//! A subset of the replicas is selected to reproduce the statistical features of the full set,
+//! following Carrazza et al. (arXiv:1504.06469). The distance between the two is measured by an
+//! error function, comparing moments, correlations, and the Kolmogorov distance of the
+//! distributions on the sampling points, and it is minimized by a genetic algorithm.
+use anyhow::{bail, Result};
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::sampling::Sampling;
+use crate::set::Set;
+use crate::uncertainty::ErrorType;
+
+/// Maximum number of points used for correlations
+const CORRELATION_POINTS: usize = 50;
+/// Random subsets used to normalize the error function
+const RANDOM_TRIALS: usize = 100;
+/// Maximum number of replicas replaced in a single mutation
+const MUTATIONS: usize = 3;
+
+/// Settings of the genetic minimization
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compression {
+    /// Number of generations
+    pub generations: usize,
+    /// Mutants generated in each generation
+    pub mutants: usize,
+    /// Seed of the random generator
+    pub seed: u64,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            generations: 1000,
+            mutants: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// Statistical estimators of a collection of replicas
+struct Estimators {
+    mean: Array1<f64>,
+    std: Array1<f64>,
+    skewness: Array1<f64>,
+    kurtosis: Array1<f64>,
+    correlations: Vec<f64>,
+    kolmogorov: Array2<f64>,
+}
+
+/// Original replicas, with their estimators
+struct Prior {
+    values: Array2<f64>,
+    correlated: Vec<usize>,
+    estimators: Estimators,
+}
+
+impl Prior {
+    /// `values` contains one row per replica, and one column per point.
+    fn new(values: Array2<f64>) -> Self {
+        // points without variance carry no information
+        let std = values.std_axis(Axis(0), 0.);
+        let informative: Vec<usize> = (0..values.ncols()).filter(|p| std[*p] > 0.).collect();
+        let values = values.select(Axis(1), &informative);
+
+        let stride = ((values.ncols() + CORRELATION_POINTS - 1) / CORRELATION_POINTS).max(1);
+        let correlated: Vec<usize> = (0..values.ncols()).step_by(stride).collect();
+
+        let all: Vec<usize> = (0..values.nrows()).collect();
+        let mean = values.mean_axis(Axis(0)).unwrap_or_default();
+        let std = values.std_axis(Axis(0), 0.);
+        let estimators = estimate(&values, &correlated, &all, &mean, &std);
+
+        Self {
+            values,
+            correlated,
+            estimators,
+        }
+    }
+
+    /// Distance of the estimators from the prior ones, separately for each estimator.
+    fn distances(&self, selection: &[usize]) -> [f64; 6] {
+        let prior = &self.estimators;
+        let subset = estimate(
+            &self.values,
+            &self.correlated,
+            selection,
+            &prior.mean,
+            &prior.std,
+        );
+        let relative = |a: ArrayView1<f64>, b: ArrayView1<f64>| {
+            a.iter()
+                .zip(b)
+                .filter(|(_, p)| **p != 0.)
+                .map(|(v, p)| ((v - p) / p).powi(2))
+                .sum::<f64>()
+        };
+        let kolmogorov = subset
+            .kolmogorov
+            .rows()
+            .into_iter()
+            .zip(prior.kolmogorov.rows())
+            .map(|(a, b)| relative(a, b))
+            .sum();
+
+        [
+            relative(subset.mean.view(), prior.mean.view()),
+            relative(subset.std.view(), prior.std.view()),
+            relative(subset.skewness.view(), prior.skewness.view()),
+            relative(subset.kurtosis.view(), prior.kurtosis.view()),
+            subset
+                .correlations
+                .iter()
+                .zip(&prior.correlations)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum(),
+            kolmogorov,
+        ]
+    }
+}
+
+/// Estimators of the `selection` of replicas, with Kolmogorov regions defined by the prior `mean`
+/// and `std`.
+fn estimate(
+    values: &Array2<f64>,
+    correlated: &[usize],
+    selection: &[usize],
+    mean: &Array1<f64>,
+    std: &Array1<f64>,
+) -> Estimators {
+    let values = values.select(Axis(0), selection);
+    let n = selection.len() as f64;
+    let average = values.mean_axis(Axis(0)).unwrap_or_default();
+    let deviations = &values - &average;
+    let moment = |k: i32| deviations.mapv(|d| d.powi(k)).sum_axis(Axis(0)) / n;
+    let variance = moment(2);
+    let deviation = variance.mapv(f64::sqrt);
+
+    let mut correlations = Vec::new();
+    for (i, a) in correlated.iter().enumerate() {
+        for b in &correlated[i + 1..] {
+            let covariance = deviations.column(*a).dot(&deviations.column(*b)) / n;
+            let norm = deviation[*a] * deviation[*b];
+            correlations.push(if norm > 0. { covariance / norm } else { 0. });
+        }
+    }
+
+    // fraction of replicas in each region, delimited in units of the prior standard deviation
+    let bounds = [-2., -1., 0., 1., 2.];
+    let mut kolmogorov = Array2::zeros((bounds.len() + 1, values.ncols()));
+    for (p, column) in values.columns().into_iter().enumerate() {
+        for v in column {
+            let region = bounds
+                .iter()
+                .take_while(|b| *v >= mean[p] + *b * std[p])
+                .count();
+            kolmogorov[[region, p]] += 1. / n;
+        }
+    }
+
+    let standardized = |k: i32| {
+        let mut moment = moment(k);
+        moment.zip_mut_with(&variance, |m, v| {
+            *m = if *v > 0. {
+                *m / v.powf(f64::from(k) / 2.)
+            } else {
+                0.
+            }
+        });
+        moment
+    };
+
+    Estimators {
+        mean: average,
+        std: deviation,
+        skewness: standardized(3),
+        kurtosis: standardized(4),
+        correlations,
+        kolmogorov,
+    }
+}
+
+/// Select `replicas` out of the rows of `values`, with one column per sampling point.
+///
+/// The selected indices are returned in increasing order, together with the value of the error
+/// function. The error function is normalized to the average over random subsets, such that
+/// values below one are better than a random selection.
+pub(crate) fn select(
+    values: Array2<f64>,
+    replicas: usize,
+    compression: &Compression,
+) -> Result<(Vec<usize>, f64)> {
+    let total = values.nrows();
+    if replicas < 2 || replicas >= total {
+        bail!(
+            "Number of compressed replicas has to be in [2, {}]",
+            total.saturating_sub(1)
+        );
+    }
+    if values.ncols() == 0 {
+        bail!("No sampling point to compare the replicas on");
+    }
+    let prior = Prior::new(values);
+    let mut rng = ChaCha8Rng::seed_from_u64(compression.seed);
+
+    let mut normalization = [0.; 6];
+    for _ in 0..RANDOM_TRIALS {
+        let selection = sample(&mut rng, total, replicas).into_vec();
+        for (n, d) in normalization.iter_mut().zip(prior.distances(&selection)) {
+            *n += d / RANDOM_TRIALS as f64;
+        }
+    }
+    let erf = |selection: &[usize]| {
+        prior
+            .distances(selection)
+            .iter()
+            .zip(&normalization)
+            .filter(|(_, n)| **n > 0.)
+            .map(|(d, n)| d / n)
+            .sum::<f64>()
+            / normalization.len() as f64
+    };
+
+    let mut best = sample(&mut rng, total, replicas).into_vec();
+    let mut best_erf = erf(&best);
+    for _ in 0..compression.generations {
+        let mut generation = None;
+        for _ in 0..compression.mutants {
+            let mut mutant = best.clone();
+            for _ in 0..rng.gen_range(1..=MUTATIONS.min(replicas)) {
+                let replacement = loop {
+                    let candidate = rng.gen_range(0..total);
+                    if !mutant.contains(&candidate) {
+                        break candidate;
+                    }
+                };
+                let position = rng.gen_range(0..replicas);
+                mutant[position] = replacement;
+            }
+            let mutant_erf = erf(&mutant);
+            if generation
+                .as_ref()
+                .map_or(true, |(_, current)| mutant_erf < *current)
+            {
+                generation = Some((mutant, mutant_erf));
+            }
+        }
+        if let Some((mutant, mutant_erf)) = generation {
+            if mutant_erf < best_erf {
+                best = mutant;
+                best_erf = mutant_erf;
+            }
+        }
+    }
+
+    best.sort_unstable();
+    Ok((best, best_erf))
+}
+
+impl Set {
+    /// Compress the replicas to a subset of `replicas`, saved with the given `name`.
+    ///
+    /// The replicas are compared on the `sampling` points. The central member of the new set is
+    /// the average of its replicas, while parameter variations are not propagated.
+    /// The new set is returned, together with the final value of the error function.
+    pub fn compress(
+        &mut self,
+        sampling: &Sampling,
+        replicas: usize,
+        compression: &Compression,
+        name: &str,
+    ) -> Result<(Set, f64)> {
+        let error_info = self.error_info()?;
+        if error_info.pdf != ErrorType::Replicas {
+            bail!("Only replicas sets can be compressed");
+        }
+        let prior = error_info.pdf_members();
+
+        let mut values = Array2::zeros((prior.len(), sampling.len()));
+        for (num, mut row) in prior.clone().zip(values.rows_mut()) {
+            row.assign(&sampling.evaluate(self, u32::try_from(num)?)?);
+        }
+
+        let (selected, erf) = select(values, replicas, compression)?;
+
+        let mut members = Vec::with_capacity(replicas);
+        for index in selected {
+            members.push(self.member(u32::try_from(prior.start + index)?)?.clone());
+        }
+
+        let info = self.derived_info(&format!(
+            "Compressed from {} to {replicas} replicas.",
+            prior.len()
+        ))?;
+        Ok((self.store_replicas(name, info, members)?, erf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::s;
+    use rand_distr::{Distribution, StandardNormal};
+
+    #[test]
+    fn selection() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let values = Array2::from_shape_fn((200, 12), |(_, p)| {
+            let r: f64 = StandardNormal.sample(&mut rng);
+            p as f64 + r * (1. + p as f64 / 4.)
+        });
+
+        let compression = Compression {
+            generations: 200,
+            ..Compression::default()
+        };
+        let (selected, erf) = select(values.clone(), 20, &compression).unwrap();
+        assert_eq!(selected.len(), 20);
+        assert!(selected.windows(2).all(|w| w[0] < w[1]));
+        // much better than a random selection
+        assert!(erf < 0.5);
+
+        // reproducible
+        assert_eq!(
+            select(values.clone(), 20, &compression).unwrap().0,
+            selected
+        );
+
+        assert!(select(values.slice(s![..10, ..]).to_owned(), 10, &compression).is_err());
+        assert!(select(Array2::zeros((200, 0)), 20, &compression).is_err());
+    }
+}
@@ -2,10 +2,14 @@
 
 pub(crate) mod cache;
 pub(crate) mod format;
+pub(crate) mod fortran;
 pub(crate) mod header;
 pub mod index;
 pub(crate) mod info;
 pub(crate) mod lhapdf;
+pub(crate) mod lhgrid;
+pub(crate) mod native;
+pub(crate) mod pds;
 pub(crate) mod resource;
 pub(crate) mod set;
 pub mod source;
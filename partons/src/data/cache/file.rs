@@ -1,13 +1,10 @@
 //! A filesystem-based cache.
 use std::fs;
-use std::io::Read;
 use std::path::PathBuf;
 
 use anyhow::bail;
 use anyhow::{anyhow, Context, Result};
 use bytes::Bytes;
-use flate2::read::GzDecoder;
-use tar::Archive;
 
 use super::super::{
     format::Format,
@@ -129,29 +126,19 @@ impl Cache {
         match resource.data {
             Data::Set(_) => {
                 let content = fs::read(&location)?;
-                let tar = GzDecoder::new(&content[..]);
-                let mut archive = Archive::new(tar);
                 if !location.pop() {
                     bail!("Parent not available");
                 };
 
                 let prefix = format!("{}.", &State::Original.marker());
-                for entry in archive.entries()? {
-                    let mut en = entry?;
-                    let inner_path = en.path()?.into_owned();
-                    let mut buf = Vec::new();
-                    let read = en.read_to_end(&mut buf)?;
-                    let bytes = Bytes::copy_from_slice(&buf);
-
-                    if read > 0 {
-                        let file_name = format.convert_name(inner_path)?;
-                        let mut path = location.clone();
-                        path.push(&(prefix.clone() + &file_name));
-
-                        fs::create_dir_all(path.parent().unwrap())?;
-                        fs::write(path, bytes)?;
-                    }
-                }
+                format.unpack(&content, |file_name, bytes| {
+                    let mut path = location.clone();
+                    path.push(&(prefix.clone() + file_name));
+
+                    fs::create_dir_all(path.parent().unwrap())?;
+                    fs::write(path, bytes)?;
+                    Ok(())
+                })?;
 
                 Ok(Bytes::new())
             }
@@ -159,6 +146,36 @@ impl Cache {
         }
     }
 
+    /// Numbers of the members of a `set` available in the given `state`.
+    pub(crate) fn members(&self, set: &str, state: &State) -> Result<Vec<u32>> {
+        let folder = self.path.join(set);
+        if !folder.exists() {
+            return Ok(Vec::new());
+        }
+
+        let prefix = match state {
+            State::Regular => String::new(),
+            State::Original => format!("{}.", state.marker()),
+        };
+        let suffix = MEMBER_PATTERN.replace(MEMBER_PLACEHOLDER, "");
+        let mut members = Vec::new();
+        for entry in fs::read_dir(folder)? {
+            let os_name = entry?.file_name();
+            let Some(num) = os_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(&suffix))
+            else {
+                continue;
+            };
+            if num.len() == 6 && num.bytes().all(|b| b.is_ascii_digit()) {
+                members.push(num.parse()?);
+            }
+        }
+        members.sort_unstable();
+        Ok(members)
+    }
+
     pub fn sets(&self) -> Result<Vec<String>> {
         let mut sets_ = Vec::new();
         for entry in fs::read_dir(&self.path)? {
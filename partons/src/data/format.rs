@@ -1,13 +1,18 @@
+use std::borrow::Cow;
+use std::io::Read;
 use std::path::PathBuf;
 
-use anyhow::{anyhow, Result};
+use anyhow::{bail, Result};
 use bytes::Bytes;
+use flate2::read::GzDecoder;
+use lz4_flex::frame::FrameDecoder;
 use serde::{Deserialize, Serialize};
+use tar::Archive;
 use thiserror::Error;
 
 use super::resource::Data;
 
-use super::lhapdf;
+use super::{lhapdf, lhgrid, native, pds};
 
 /// Error during data conversion
 #[derive(Error, Debug)]
@@ -16,15 +21,21 @@ pub enum ConversionError {
     #[error("Missing field {0}")]
     MissingField(String),
     /// Type mismatched
-    #[error("Missing field {0}")]
+    #[error("Wrong type for field {0}")]
     FieldType(String),
 }
 
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) enum Format {
     Native,
     Lhapdf,
+    /// LHAPDF5 `.LHgrid` files in the NNPDF interpolated layout, possibly gzipped
+    NnpdfLhgrid,
+    /// Gzipped tar archives of CTEQ `.pds` tables
+    Pds,
 }
 
 impl Default for Format {
@@ -36,23 +47,72 @@ impl Default for Format {
 impl Format {
     pub(crate) fn convert(&self, content: Bytes, data: &Data) -> Result<Bytes> {
         match self {
-            Self::Native => {
-                // TODO: move the content from original to new
-                Ok(content)
-            }
+            // legacy formats are already converted while unpacking
+            Self::Native | Self::NnpdfLhgrid | Self::Pds => native::convert(content, data),
             Self::Lhapdf => lhapdf::convert(content, data),
         }
     }
 
-    pub(crate) fn convert_name(&self, path: PathBuf) -> Result<String> {
+    /// Convert native `content` back to the format.
+    pub(crate) fn revert(&self, content: Bytes, data: &Data) -> Result<Bytes> {
+        match self {
+            Self::Native => Ok(content),
+            Self::Lhapdf => lhapdf::revert(content, data),
+            Self::NnpdfLhgrid | Self::Pds => bail!("Writing {self:?} files is not supported"),
+        }
+    }
+
+    /// Decompress a set archive, exposing the tar content.
+    fn decoder<'a>(&self, content: &'a [u8]) -> Box<dyn Read + 'a> {
+        match self {
+            Self::Native => Box::new(FrameDecoder::new(content)),
+            Self::Lhapdf | Self::NnpdfLhgrid | Self::Pds => Box::new(GzDecoder::new(content)),
+        }
+    }
+
+    /// Extract the files of a set from its downloaded `content`.
+    ///
+    /// Each file is passed to `write` with its name in the cache layout.
+    pub(crate) fn unpack(
+        &self,
+        content: &[u8],
+        mut write: impl FnMut(&str, &[u8]) -> Result<()>,
+    ) -> Result<()> {
         match self {
-            Self::Native => path
-                .file_name()
-                .map(|s| s.to_str())
-                .flatten()
-                .map(|s| s.to_owned())
-                .ok_or(anyhow!("...")),
-            Self::Lhapdf => lhapdf::convert_name(path),
+            Self::Native => entries(self.decoder(content), |path, buf| {
+                write(&native::convert_name(path)?, buf)
+            }),
+            Self::Lhapdf => entries(self.decoder(content), |path, buf| {
+                write(&lhapdf::convert_name(path)?, buf)
+            }),
+            Self::NnpdfLhgrid => {
+                let content = if content.starts_with(&GZIP_MAGIC) {
+                    let mut decompressed = Vec::new();
+                    self.decoder(content).read_to_end(&mut decompressed)?;
+                    Cow::Owned(decompressed)
+                } else {
+                    Cow::Borrowed(content)
+                };
+                lhgrid::unpack(&content, &mut write)
+            }
+            Self::Pds => pds::unpack(self.decoder(content), &mut write),
+        }
+    }
+}
+
+/// Pass the path and content of each non-empty file of a tar `archive` to `f`.
+pub(crate) fn entries(
+    archive: impl Read,
+    mut f: impl FnMut(PathBuf, &[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut archive = Archive::new(archive);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut buf = Vec::new();
+        if entry.read_to_end(&mut buf)? > 0 {
+            f(path, &buf)?;
         }
     }
+    Ok(())
 }
@@ -0,0 +1,97 @@
+//! Read Fortran formatted tables
+//!
+//! Legacy formats were written and read by Fortran programs, using list-directed input: numbers
+//! are separated by blanks or commas, each read starts on a new line and may span several, and
+//! the rest of its last line is discarded.
+use std::str::Lines;
+
+use anyhow::{anyhow, Result};
+
+/// Parse a floating point number, accepting also Fortran `D` exponents.
+pub(crate) fn float(token: &str) -> Option<f64> {
+    token
+        .parse()
+        .ok()
+        .or_else(|| token.replace(['D', 'd'], "e").parse().ok())
+}
+
+/// Blank or comma separated tokens of a `line`.
+pub(crate) fn tokens(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+}
+
+/// Sequential reader of the lines of a table
+pub(crate) struct Records<'a> {
+    lines: Lines<'a>,
+    line: usize,
+}
+
+impl<'a> Records<'a> {
+    pub(crate) fn new(content: &'a str) -> Self {
+        Self {
+            lines: content.lines(),
+            line: 0,
+        }
+    }
+
+    /// Number of the last line read, counted from one.
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Read a whole line, as text.
+    pub(crate) fn text(&mut self) -> Result<&'a str> {
+        let line = self
+            .lines
+            .next()
+            .ok_or_else(|| anyhow!("Unexpected end of file after line {}", self.line))?;
+        self.line += 1;
+        Ok(line)
+    }
+
+    /// Read `n` numbers, spanning as many lines as needed.
+    pub(crate) fn numbers(&mut self, n: usize) -> Result<Vec<f64>> {
+        let mut numbers = Vec::with_capacity(n);
+        while numbers.len() < n {
+            let line = self.text()?;
+            for token in tokens(line).take(n - numbers.len()) {
+                numbers.push(
+                    float(token)
+                        .ok_or_else(|| anyhow!("Line {}: invalid number '{token}'", self.line))?,
+                );
+            }
+        }
+        Ok(numbers)
+    }
+
+    /// Read a single non-negative integer.
+    pub(crate) fn count(&mut self) -> Result<usize> {
+        let number = self.numbers(1)?[0];
+        to_count(number).ok_or_else(|| anyhow!("Line {}: invalid count {number}", self.line))
+    }
+}
+
+/// Convert a number read as floating point to a count.
+pub(crate) fn to_count(number: f64) -> Option<usize> {
+    (number >= 0. && number.fract() == 0.).then_some(number as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() {
+        let mut records = Records::new("title\n 1, 2.5D0\n 3 4 5\n 6\n 7 8\n");
+        assert_eq!(records.text().unwrap(), "title");
+        assert_eq!(records.numbers(4).unwrap(), vec![1., 2.5, 3., 4.]);
+        // the rest of the line is discarded
+        assert_eq!(records.count().unwrap(), 6);
+        assert!(records.count().is_ok());
+        assert_eq!(records.line(), 5);
+        assert!(records.text().is_err());
+        assert!(Records::new("1 x").numbers(2).is_err());
+        assert!(Records::new("1.5").count().is_err());
+    }
+}
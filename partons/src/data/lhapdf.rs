@@ -1,14 +1,19 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bytes::Bytes;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 use super::cache::file::{INFO_NAME, MEMBER_PATTERN, MEMBER_PLACEHOLDER};
+use super::format::Format;
 use super::resource::Data;
+use crate::extrapolation::Extrapolator;
 use crate::info::Info;
-use crate::member::MemberWrapper;
+use crate::interpolation::Interpolator;
+use crate::member::{Member, EXTRAPOLATOR_KEY, INTERPOLATOR_KEY};
+use crate::set::Set;
 
 pub(crate) mod grid;
 pub(crate) mod info;
@@ -21,15 +26,61 @@ pub(crate) fn convert(bytes: Bytes, data: &Data) -> Result<Bytes> {
             Ok(Bytes::copy_from_slice(&raw_bytes))
         }
         Data::Member(_, _) => {
-            let converted = grid::Grid::load(bytes)?.try_into()?;
-            let wrapper = MemberWrapper { member: converted };
-            let raw_bytes = bincode::encode_to_vec(wrapper, bincode::config::standard())?;
-            Ok(Bytes::copy_from_slice(&raw_bytes))
+            let grid = grid::Grid::load(bytes).with_context(|| {
+                format!("Failed to parse {}", revert_name(data).unwrap_or_default())
+            })?;
+            let converted: Member = grid.try_into()?;
+            converted.dump()
         }
         _ => Ok(bytes),
     }
 }
 
+/// Convert native content back to the LHAPDF format.
+pub(crate) fn revert(bytes: Bytes, data: &Data) -> Result<Bytes> {
+    match data {
+        Data::Info(_) => Ok(info::dump(&Info::load(bytes)?)?.into()),
+        Data::Member(_, _) => Ok(grid::dump(&Member::load(bytes)?)?.into()),
+        _ => Ok(bytes),
+    }
+}
+
+/// Canonical LHAPDF name of the interpolation or extrapolation strategy, for the metadata `key`.
+///
+/// Any other metadata is returned unchanged.
+pub(crate) fn strategy(key: &str, value: &str) -> Result<String> {
+    Ok(match key {
+        INTERPOLATOR_KEY => value.parse::<Interpolator>()?.to_string(),
+        EXTRAPOLATOR_KEY => match value.parse()? {
+            Extrapolator::Zero => bail!("Extrapolator '{value}' not supported by LHAPDF"),
+            extrapolator => extrapolator.to_string(),
+        },
+        _ => value.to_owned(),
+    })
+}
+
+/// Write a set in the LHAPDF format, in a folder named after the set, within `path`.
+pub(crate) fn export(set: &mut Set, path: &Path) -> Result<PathBuf> {
+    let name = set.name();
+    let folder = path.join(&name);
+    fs::create_dir_all(&folder)?;
+
+    let size = set.size()?;
+    let mut info = set.info()?.clone();
+    info.complete(set.member(0)?, size);
+    let data = Data::Info(name.clone());
+    let content = Format::Lhapdf.revert(serde_yaml::to_string(&info)?.into(), &data)?;
+    fs::write(folder.join(revert_name(&data)?), content)?;
+
+    for num in 0..size {
+        let data = Data::Member(name.clone(), num);
+        let content = Format::Lhapdf.revert(set.member(num)?.dump()?, &data)?;
+        fs::write(folder.join(revert_name(&data)?), content)?;
+    }
+
+    Ok(folder)
+}
+
 lazy_static! {
     static ref MEMBER_REGEX: Regex = Regex::new(r".*_(\d{4}).dat").unwrap();
 }
@@ -51,3 +102,12 @@ pub(crate) fn convert_name(path: PathBuf) -> Result<String> {
         bail!("...")
     }
 }
+
+/// LHAPDF file name of the given `data`.
+pub(crate) fn revert_name(data: &Data) -> Result<String> {
+    match data {
+        Data::Info(set) => Ok(format!("{set}.info")),
+        Data::Member(set, num) => Ok(format!("{set}_{num:04}.dat")),
+        _ => bail!("No LHAPDF file for {data}"),
+    }
+}
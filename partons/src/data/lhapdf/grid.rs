@@ -1,14 +1,16 @@
 //! Parse legacy LHAPDF member files
 
-use std::fmt::Debug;
-use std::str::FromStr;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Write};
 
-use anyhow::{bail, Result};
+use anyhow::Result;
 use bytes::Bytes;
-use ndarray::{Array1, Array2, Array3};
+use ndarray::{Array1, Array3};
+use thiserror::Error;
 
 use crate::block::Block;
 use crate::data::format::ConversionError;
+use crate::data::fortran::float;
 use crate::member::{Member, Metadata};
 
 pub(crate) struct Grid {
@@ -16,99 +18,320 @@ pub(crate) struct Grid {
     blocks: Vec<Block>,
 }
 
-impl Grid {
-    fn metadata(section: Option<&str>) -> Result<Metadata> {
-        if let Some(header) = section {
-            Ok(serde_yaml::from_slice(header.as_bytes())?)
-        } else {
-            bail!("No section found in grid file.")
-        }
-    }
+/// Location in a grid file
+///
+/// Both blocks and lines are counted from one, and lines are counted from the beginning of the
+/// file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Block number, `0` standing for the header
+    pub block: usize,
+    /// Line number
+    pub line: usize,
+}
 
-    fn sequence<T: FromStr>(line: Option<&str>) -> Result<Array1<T>>
-    where
-        <T as FromStr>::Err: Debug,
-    {
-        if let Some(text) = line {
-            let nums = text
-                .trim()
-                .split(" ")
-                .into_iter()
-                .map(|v| str::parse(v).unwrap())
-                .collect();
-            Ok(Array1::from_vec(nums))
-        } else {
-            bail!("");
-        }
+impl Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {}, line {}", self.block, self.line)
     }
+}
 
-    fn table(lines: Option<&str>) -> Result<Array2<f64>> {
-        if let Some(text) = lines {
-            let mut rows = Vec::new();
-            for line in text.lines() {
-                let nums: Vec<f64> = line
-                    .trim()
-                    .split(" ")
-                    .filter(|v| v.len() > 0)
-                    .into_iter()
-                    .map(|v| str::parse(v).unwrap())
-                    .collect();
-                rows.push(nums);
-            }
-            let shape = (rows.len(), rows[0].len());
-            let values = rows.concat();
-            Ok(Array2::from_shape_vec(shape, values)?)
-        } else {
-            bail!("")
-        }
-    }
+/// Error parsing a grid file
+#[derive(Error, Debug, PartialEq)]
+pub enum GridError {
+    /// Malformed header
+    #[error("{0}: invalid header, {1}")]
+    Header(Position, String),
+    /// Token not representing a number
+    #[error("{0}: invalid number '{1}'")]
+    InvalidNumber(Position, String),
+    /// Block ended before one of its parts
+    #[error("{0}: missing {1}")]
+    Missing(Position, &'static str),
+    /// Values row not matching the flavors
+    #[error("{position}: {found} values found, instead of {expected}, one per flavor")]
+    RowLength {
+        /// Location of the row
+        position: Position,
+        /// Number of flavors
+        expected: usize,
+        /// Number of values
+        found: usize,
+    },
+    /// Values rows not matching the knots
+    #[error("{position}: {found} rows found, instead of {expected}, one per pair of knots")]
+    RowCount {
+        /// Location of the block end
+        position: Position,
+        /// Number of `(x, Q)` pairs
+        expected: usize,
+        /// Number of rows
+        found: usize,
+    },
+}
 
-    fn values(values: Array2<f64>, xs: usize) -> Result<Array3<f64>> {
-        let &[points, pids] = values.shape() else { bail!("") };
-        let mu2s = points / xs;
-        let lha_shaped = values.into_shape((xs, mu2s, pids))?;
-        // TODO: solve the following horrible memory duplication, and horrible loop
-        let mut native_shaped = Array3::zeros((pids, xs, mu2s));
-        for ((x, mu, p), val) in lha_shaped.indexed_iter() {
-            native_shaped[(p, x, mu)] = *val;
-        }
-        Ok(native_shaped)
+impl Grid {
+    fn sequence<T>(
+        (line, text): (usize, &str),
+        block: usize,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<Vec<T>, GridError> {
+        text.split_whitespace()
+            .map(|token| {
+                parse(token).ok_or_else(|| {
+                    GridError::InvalidNumber(Position { block, line }, token.to_owned())
+                })
+            })
+            .collect()
     }
 
-    fn block(section: &str) -> Result<Block> {
-        let mut split = section.trim().splitn(4, '\n');
+    fn block<'a>(
+        lines: &mut impl Iterator<Item = (usize, &'a str)>,
+        block: usize,
+        start: usize,
+    ) -> Result<Option<Block>, GridError> {
+        let mut lines = lines
+            .take_while(|(_, text)| text.trim() != "---")
+            .filter(|(_, text)| !text.trim().is_empty());
+        let mut end = start;
+        let mut next = |part| {
+            lines
+                .next()
+                .map(|(line, text)| {
+                    end = line;
+                    (line, text)
+                })
+                .ok_or(GridError::Missing(Position { block, line: end }, part))
+        };
 
-        let xgrid = Self::sequence(split.next())?;
-        let mu2grid = Self::sequence(split.next())?;
-        let pids = Self::sequence(split.next())?;
+        // a trailing separator closes the last block
+        let Ok(xline) = next("x knots") else {
+            return Ok(None);
+        };
+        let xgrid = Self::sequence(xline, block, float)?;
+        // LHAPDF grids store the scale Q, while blocks are sampled in mu2
+        let qgrid = Self::sequence(next("Q knots")?, block, float)?;
+        let pids = Self::sequence(next("flavors")?, block, |t| t.parse().ok())?;
 
-        let values = Self::values(Self::table(split.next())?, xgrid.len())?;
+        // LHAPDF rows run over Q faster than x, with one column per flavor, and they are written
+        // in place in the block layout
+        let expected = xgrid.len() * qgrid.len();
+        let mut values = Array3::zeros((pids.len(), xgrid.len(), qgrid.len()));
+        let mut rows = 0;
+        while let Ok((line, text)) = next("values") {
+            let (ix, imu) = (rows / qgrid.len().max(1), rows % qgrid.len().max(1));
+            let mut found = 0;
+            for token in text.split_whitespace() {
+                let value = float(token).ok_or_else(|| {
+                    GridError::InvalidNumber(Position { block, line }, token.to_owned())
+                })?;
+                if found < pids.len() && rows < expected {
+                    values[(found, ix, imu)] = value;
+                }
+                found += 1;
+            }
+            if found != pids.len() {
+                return Err(GridError::RowLength {
+                    position: Position { block, line },
+                    expected: pids.len(),
+                    found,
+                });
+            }
+            rows += 1;
+        }
+        if rows != expected || expected == 0 {
+            return Err(GridError::RowCount {
+                position: Position { block, line: end },
+                expected,
+                found: rows,
+            });
+        }
 
-        Ok(Block::new(pids, xgrid, mu2grid, values))
+        let mu2grid = qgrid.iter().map(|q| q * q).collect();
+        Ok(Some(Block::new(
+            Array1::from_vec(pids),
+            Array1::from_vec(xgrid),
+            Array1::from_vec(mu2grid),
+            values,
+        )))
     }
 
     pub(crate) fn load(bytes: Bytes) -> Result<Self> {
-        let content = String::from_utf8(bytes.into())?;
-        let mut sections = content.trim().split_terminator("---");
+        // only validated, the lines borrow from the original buffer
+        let content = std::str::from_utf8(&bytes)?;
+        let mut lines = content.lines().enumerate().map(|(i, text)| (i + 1, text));
 
-        let metadata = Self::metadata(sections.next())?;
+        let header: Vec<_> = lines
+            .by_ref()
+            .take_while(|(_, text)| text.trim() != "---")
+            .map(|(_, text)| text)
+            .collect();
+        let header = header.join("\n");
+        let metadata = if header.trim().is_empty() {
+            Metadata::new()
+        } else {
+            serde_yaml::from_str(&header).map_err(|err| {
+                let line = err.location().map_or(1, |l| l.line());
+                GridError::Header(Position { block: 0, line }, err.to_string())
+            })?
+        };
 
         let mut blocks = Vec::new();
-        for section in sections {
-            blocks.push(Self::block(section)?);
+        let mut lines = lines.peekable();
+        while let Some((start, _)) = lines.peek().copied() {
+            if let Some(block) = Self::block(&mut lines, blocks.len() + 1, start)? {
+                blocks.push(block);
+            }
         }
 
         Ok(Self { metadata, blocks })
     }
 }
 
+/// Grid format tag, required in the member header
+const FORMAT: &str = "lhagrid1";
+
+/// Join `values` with spaces, in their shortest exact representation.
+fn line<T: Display>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Serialize a member as an LHAPDF grid file.
+pub(crate) fn dump(member: &Member) -> Result<String> {
+    let mut metadata = BTreeMap::new();
+    for (key, value) in &member.metadata {
+        metadata.insert(key.as_str(), super::strategy(key, value)?);
+    }
+    metadata.insert("Format", FORMAT.to_owned());
+
+    let mut content = serde_yaml::to_string(&metadata)?;
+    content.push_str("---\n");
+    for block in &member.blocks {
+        let exponential = |values: &Array1<f64>| line(values.iter().map(|v| format!("{v:e}")));
+        writeln!(content, "{}", exponential(&block.xgrid))?;
+        // LHAPDF grids store the scale Q, while blocks are sampled in mu2
+        writeln!(content, "{}", exponential(&block.mu2grid.mapv(f64::sqrt)))?;
+        writeln!(content, "{}", line(&block.pids))?;
+        for ix in 0..block.xgrid.len() {
+            for imu in 0..block.mu2grid.len() {
+                let values =
+                    (0..block.pids.len()).map(|p| format!("{:e}", block.values[(p, ix, imu)]));
+                writeln!(content, "{}", line(values))?;
+            }
+        }
+        content.push_str("---\n");
+    }
+
+    Ok(content)
+}
+
 impl TryFrom<Grid> for Member {
     type Error = ConversionError;
 
     fn try_from(value: Grid) -> Result<Self, Self::Error> {
+        // members rely on blocks sorted by scale, to select them
+        let mut blocks = value.blocks;
+        blocks.sort_by(|a, b| a.mu2grid[0].total_cmp(&b.mu2grid[0]));
+
         Ok(Member {
             metadata: value.metadata,
-            blocks: value.blocks,
+            blocks,
+            interpolator: None,
+            extrapolator: None,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::{array, Array};
+
+    #[test]
+    fn roundtrip() {
+        let xgrid: Array1<f64> = Array::geomspace(1e-7, 1., 11).unwrap();
+        let mu2grid = array![1.65, 4.92, 25.].mapv(|q: f64| q * q);
+        let values = Array3::from_shape_fn((2, 11, 3), |(p, i, j)| {
+            (p as f64 + 1.) * xgrid[i].powf(0.3) / mu2grid[j].ln()
+        });
+        let member = Member {
+            metadata: Metadata::from([("PdfType".to_owned(), "central".to_owned())]),
+            blocks: vec![Block::new(array![21, 2], xgrid, mu2grid, values)],
+            interpolator: None,
+            extrapolator: None,
+        };
+
+        let content = dump(&member).unwrap();
+        let loaded: Member = Grid::load(Bytes::from(content))
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(loaded.metadata["Format"], FORMAT);
+        assert_eq!(loaded.metadata["PdfType"], "central");
+        let (block, original) = (&loaded.blocks[0], &member.blocks[0]);
+        assert_eq!(block.pids, original.pids);
+        assert_eq!(block.xgrid, original.xgrid);
+        assert_eq!(block.values, original.values);
+        for (mu2, expected) in block.mu2grid.iter().zip(&original.mu2grid) {
+            assert!((mu2 / expected - 1.).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn lenient() {
+        let content = "PdfType: central\n---\n1e-3\t0.1  1.0\n1.0 10.0\n21 1\n\
+            1D0 2d0\n3 4\n5 6\n7 8\n9 10\n11 12\n---\n";
+        let grid = Grid::load(Bytes::from(content)).unwrap();
+        let block = &grid.blocks[0];
+        assert_eq!(block.xgrid, array![1e-3, 0.1, 1.]);
+        assert_eq!(block.mu2grid, array![1., 100.]);
+        // rows run over Q first, with one column per flavor
+        assert_eq!(block.values[(0, 0, 1)], 3.);
+        assert_eq!(block.values[(1, 2, 0)], 10.);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |content: &str| {
+            Grid::load(Bytes::from(format!(
+                "PdfType: central\n---\n{content}---\n"
+            )))
+            .err()
+            .unwrap()
+            .downcast::<GridError>()
+            .unwrap()
+        };
+        let position = |block, line| Position { block, line };
+
+        assert_eq!(
+            error("0.1 1.0\n1.0 10.0\n21\n1\n2\nx\n4\n"),
+            GridError::InvalidNumber(position(1, 8), "x".to_owned())
+        );
+        assert_eq!(
+            error("0.1 1.0\n1.0 10.0\n21\n1\n2 2\n3\n4\n"),
+            GridError::RowLength {
+                position: position(1, 7),
+                expected: 1,
+                found: 2
+            }
+        );
+        assert_eq!(
+            error("0.1 1.0\n1.0 10.0\n21\n1\n2\n3\n"),
+            GridError::RowCount {
+                position: position(1, 8),
+                expected: 4,
+                found: 3
+            }
+        );
+        assert_eq!(
+            error("0.1 1.0\n1.0 10.0\n"),
+            GridError::Missing(position(1, 4), "flavors")
+        );
+    }
+}
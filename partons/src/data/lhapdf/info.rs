@@ -2,10 +2,10 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 use bytes::Bytes;
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
 
 use crate::data::format::ConversionError::{self, FieldType, MissingField};
-use crate::info;
+use crate::info::{self, PID};
 
 pub struct Info(HashMap<String, Value>);
 
@@ -13,6 +13,16 @@ impl Info {
     pub(crate) fn load(bytes: Bytes) -> Result<Self> {
         Ok(Self(serde_yaml::from_slice(&bytes)?))
     }
+
+    /// Collect the string keys of a `mapping`, as read from an info file.
+    pub(crate) fn from_mapping(mapping: Mapping) -> Self {
+        Self(
+            mapping
+                .into_iter()
+                .filter_map(|(key, value)| Some((key.as_str()?.to_owned(), value)))
+                .collect(),
+        )
+    }
 }
 
 macro_rules! extract {
@@ -20,34 +30,51 @@ macro_rules! extract {
         let wrong_type = Err(FieldType(stringify!($name).to_owned()));
         let $name = $value
             .0
-            .get($lha_name)
+            .remove($lha_name)
             .map(|v| {
-                let Value::$type(num) = v else { return wrong_type};
-                Ok(num)
+                let Value::$type(inner) = v else {
+                    return wrong_type;
+                };
+                Ok(inner)
             })
             .transpose()?;
     };
 }
 
+macro_rules! number {
+    ($name:ident, $as:ident) => {
+        let $name = $name
+            .map(|n| n.$as().ok_or(FieldType(stringify!($name).to_owned())))
+            .transpose()?;
+    };
+}
+
 macro_rules! convert {
     ($name:ident: u64 = $value:ident[$lha_name:literal]) => {
         extract!($name, Number, $lha_name, $value);
-        let $name = $name.map(|n| n.as_u64().unwrap());
+        number!($name, as_u64);
     };
     ($name:ident: i64 = $value:ident[$lha_name:literal]) => {
         extract!($name, Number, $lha_name, $value);
-        let $name = $name.map(|n| n.as_i64().unwrap());
+        number!($name, as_i64);
     };
     ($name:ident: PID = $value:ident[$lha_name:literal]) => {
         convert!($name: i64 = $value[$lha_name]);
     };
     ($name:ident: f64 = $value:ident[$lha_name:literal]) => {
         extract!($name, Number, $lha_name, $value);
-        let $name = $name.map(|n| n.as_f64().unwrap());
+        number!($name, as_f64);
     };
     ($name:ident: String = $value:ident[$lha_name:literal]) => {
         extract!($name, String, $lha_name, $value);
-        let $name = $name.map(|s| s.to_owned());
+    };
+    ($name:ident: Vec<$type:ident> = $value:ident[$lha_name:literal]) => {
+        extract!($name, Sequence, $lha_name, $value);
+        let $name: Vec<$type> = $name
+            .map(|seq| serde_yaml::from_value(Value::Sequence(seq)))
+            .transpose()
+            .map_err(|_| FieldType(stringify!($name).to_owned()))?
+            .unwrap_or_default();
     };
     (Some($name:ident: $type:ident) = $value:ident[$lha_name:literal]) => {
         convert!($name: $type = $value[$lha_name]);
@@ -56,11 +83,10 @@ macro_rules! convert {
     };
 }
 
-// let missing = ;
 impl TryFrom<Info> for info::Info {
     type Error = ConversionError;
 
-    fn try_from(value: Info) -> Result<Self, Self::Error> {
+    fn try_from(mut value: Info) -> Result<Self, Self::Error> {
         convert!(id: u64 = value["SetIndex"]);
         convert!(Some(description: String) = value["SetDesc"]);
         convert!(Some(authors: String) = value["Authors"]);
@@ -69,8 +95,34 @@ impl TryFrom<Info> for info::Info {
         convert!(particle: PID = value["Particle"]);
         convert!(Some(order_qcd: u64) = value["OrderQCD"]);
         convert!(error_type: String = value["ErrorType"]);
-        convert!(data_version: i64 = value["Note"]);
+        convert!(data_version: i64 = value["DataVersion"]);
         convert!(note: String = value["Note"]);
+        convert!(flavors: Vec<PID> = value["Flavors"]);
+        convert!(num_members: u64 = value["NumMembers"]);
+        convert!(x_min: f64 = value["XMin"]);
+        convert!(x_max: f64 = value["XMax"]);
+        convert!(q_min: f64 = value["QMin"]);
+        convert!(q_max: f64 = value["QMax"]);
+        convert!(error_conf_level: f64 = value["ErrorConfLevel"]);
+        convert!(flavor_scheme: String = value["FlavorScheme"]);
+        convert!(num_flavors: u64 = value["NumFlavors"]);
+        convert!(mz: f64 = value["MZ"]);
+        convert!(m_up: f64 = value["MUp"]);
+        convert!(m_down: f64 = value["MDown"]);
+        convert!(m_strange: f64 = value["MStrange"]);
+        convert!(m_charm: f64 = value["MCharm"]);
+        convert!(m_bottom: f64 = value["MBottom"]);
+        convert!(m_top: f64 = value["MTop"]);
+        convert!(alphas_mz: f64 = value["AlphaS_MZ"]);
+        convert!(alphas_order_qcd: u64 = value["AlphaS_OrderQCD"]);
+        convert!(alphas_num_flavors: u64 = value["AlphaS_NumFlavors"]);
+        convert!(alphas_type: String = value["AlphaS_Type"]);
+        convert!(alphas_qs: Vec<f64> = value["AlphaS_Qs"]);
+        convert!(alphas_vals: Vec<f64> = value["AlphaS_Vals"]);
+        convert!(alphas_lambda3: f64 = value["AlphaS_Lambda3"]);
+        convert!(alphas_lambda4: f64 = value["AlphaS_Lambda4"]);
+        convert!(alphas_lambda5: f64 = value["AlphaS_Lambda5"]);
+        convert!(alphas_lambda6: f64 = value["AlphaS_Lambda6"]);
         Ok(info::Info {
             id,
             description,
@@ -82,7 +134,181 @@ impl TryFrom<Info> for info::Info {
             error_type,
             data_version,
             note,
+            flavors,
+            num_members,
+            x_min,
+            x_max,
+            q_min,
+            q_max,
+            error_conf_level,
+            flavor_scheme,
+            num_flavors,
+            mz,
+            m_up,
+            m_down,
+            m_strange,
+            m_charm,
+            m_bottom,
+            m_top,
+            alphas_mz,
+            alphas_order_qcd,
+            alphas_num_flavors,
+            alphas_type,
+            alphas_qs,
+            alphas_vals,
+            alphas_lambda3,
+            alphas_lambda4,
+            alphas_lambda5,
+            alphas_lambda6,
             more_members: value.0,
         })
     }
 }
+
+macro_rules! revert {
+    ($mapping:ident[$lha_name:literal] = $value:expr) => {
+        if let Some(value) = $value {
+            $mapping.insert($lha_name.into(), serde_yaml::to_value(value)?);
+        }
+    };
+}
+
+fn nonempty<T>(seq: &[T]) -> Option<&[T]> {
+    (!seq.is_empty()).then_some(seq)
+}
+
+/// Metadata keyed by their LHAPDF names.
+///
+/// The standard fields come first, in the conventional LHAPDF order, followed by the
+/// unstructured ones.
+pub(crate) fn mapping(info: &info::Info) -> Result<Mapping> {
+    let mut mapping = Mapping::new();
+    revert!(mapping["SetDesc"] = Some(&info.description));
+    revert!(mapping["SetIndex"] = info.id);
+    revert!(mapping["Authors"] = Some(&info.authors));
+    revert!(mapping["Reference"] = &info.reference);
+    revert!(mapping["Year"] = info.year);
+    revert!(mapping["Format"] = Some("lhagrid1"));
+    revert!(mapping["DataVersion"] = info.data_version);
+    revert!(mapping["Note"] = &info.note);
+    revert!(mapping["NumMembers"] = info.num_members);
+    revert!(mapping["Particle"] = info.particle);
+    revert!(mapping["Flavors"] = nonempty(&info.flavors));
+    revert!(mapping["OrderQCD"] = Some(info.order.0));
+    revert!(mapping["FlavorScheme"] = &info.flavor_scheme);
+    revert!(mapping["NumFlavors"] = info.num_flavors);
+    revert!(mapping["ErrorType"] = &info.error_type);
+    revert!(mapping["ErrorConfLevel"] = info.error_conf_level);
+    revert!(mapping["XMin"] = info.x_min);
+    revert!(mapping["XMax"] = info.x_max);
+    revert!(mapping["QMin"] = info.q_min);
+    revert!(mapping["QMax"] = info.q_max);
+    revert!(mapping["MZ"] = info.mz);
+    revert!(mapping["MUp"] = info.m_up);
+    revert!(mapping["MDown"] = info.m_down);
+    revert!(mapping["MStrange"] = info.m_strange);
+    revert!(mapping["MCharm"] = info.m_charm);
+    revert!(mapping["MBottom"] = info.m_bottom);
+    revert!(mapping["MTop"] = info.m_top);
+    revert!(mapping["AlphaS_MZ"] = info.alphas_mz);
+    revert!(mapping["AlphaS_OrderQCD"] = info.alphas_order_qcd);
+    revert!(mapping["AlphaS_NumFlavors"] = info.alphas_num_flavors);
+    revert!(mapping["AlphaS_Type"] = &info.alphas_type);
+    revert!(mapping["AlphaS_Qs"] = nonempty(&info.alphas_qs));
+    revert!(mapping["AlphaS_Vals"] = nonempty(&info.alphas_vals));
+    revert!(mapping["AlphaS_Lambda3"] = info.alphas_lambda3);
+    revert!(mapping["AlphaS_Lambda4"] = info.alphas_lambda4);
+    revert!(mapping["AlphaS_Lambda5"] = info.alphas_lambda5);
+    revert!(mapping["AlphaS_Lambda6"] = info.alphas_lambda6);
+
+    let mut extra: Vec<_> = info.more_members.iter().collect();
+    extra.sort_by_key(|(key, _)| key.as_str());
+    for (key, value) in extra {
+        mapping.insert(key.as_str().into(), value.clone());
+    }
+
+    Ok(mapping)
+}
+
+/// Serialize the metadata as an LHAPDF info file.
+pub(crate) fn dump(info: &info::Info) -> Result<String> {
+    let mut mapping = mapping(info)?;
+    for (key, value) in &mut mapping {
+        if let (Value::String(key), Value::String(name)) = (key, value) {
+            *name = super::strategy(key, name)?;
+        }
+    }
+
+    Ok(serde_yaml::to_string(&mapping)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed() {
+        let yaml = "
+SetDesc: 'A set'
+Authors: Somebody
+OrderQCD: 2
+DataVersion: 3
+Flavors: [-3, -2, -1, 1, 2, 3, 21]
+NumMembers: 101
+XMin: 1.0e-9
+QMax: 1.0e5
+MCharm: 1.51
+AlphaS_MZ: 0.118
+AlphaS_Qs: [1.65, 10.0]
+AlphaS_Vals: [0.33, 0.18]
+Interpolator: logcubic
+";
+        let info = info::Info::try_from(Info::load(Bytes::from(yaml)).unwrap()).unwrap();
+
+        assert_eq!(info.data_version, Some(3));
+        assert_eq!(info.flavors, vec![-3, -2, -1, 1, 2, 3, 21]);
+        assert_eq!(info.num_members, Some(101));
+        assert_eq!((info.x_min, info.q_max), (Some(1e-9), Some(1e5)));
+        assert_eq!((info.m_charm, info.m_top), (Some(1.51), None));
+        assert_eq!(info.alphas_vals, vec![0.33, 0.18]);
+        // only the fields without a typed counterpart are kept
+        assert_eq!(info.more_members.len(), 1);
+        assert!(info.more_members.contains_key("Interpolator"));
+
+        let wrong = "SetDesc: a\nAuthors: b\nOrderQCD: 1\nNumMembers: 1.5";
+        assert!(info::Info::try_from(Info::load(Bytes::from(wrong)).unwrap()).is_err());
+    }
+
+    #[test]
+    fn roundtrip() {
+        let yaml = "
+SetDesc: 'A set'
+Authors: Somebody
+OrderQCD: 1
+Flavors: [-1, 1, 21]
+NumMembers: 3
+QMin: 1.65
+AlphaS_Vals: [0.33, 0.18]
+Interpolator: logbicubic
+Extra: [1, 2]
+";
+        let original = info::Info::try_from(Info::load(Bytes::from(yaml)).unwrap()).unwrap();
+        let content = dump(&original).unwrap();
+        assert!(content.contains("Format: lhagrid1"));
+        // strategies are written with the names known to LHAPDF
+        assert!(content.contains("Interpolator: logcubic"));
+
+        let info = info::Info::try_from(Info::load(Bytes::from(content)).unwrap()).unwrap();
+        assert_eq!(info.description, original.description);
+        assert_eq!(info.flavors, original.flavors);
+        assert_eq!((info.num_members, info.q_min), (Some(3), Some(1.65)));
+        assert_eq!(info.alphas_vals, original.alphas_vals);
+        assert_eq!(info.more_members["Extra"], original.more_members["Extra"]);
+
+        let mut unsupported = original;
+        unsupported
+            .more_members
+            .insert("Extrapolator".to_owned(), Value::from("zero"));
+        assert!(dump(&unsupported).is_err());
+    }
+}
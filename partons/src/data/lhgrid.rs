@@ -0,0 +1,268 @@
+//! Import LHAPDF5 NNPDF grids
+//!
+//! LHAPDF5 sets are single `.LHgrid` files, containing all the members. The file is divided in
+//! sections, introduced by quoted tags, e.g. `'Description:'` or `'Evolution:'`, and terminated
+//! by `'End:'`. The layout of the grid, following the evolution code, depends on the group which
+//! produced the set. Only the interpolated NNPDF one is supported, hence the name of the format,
+//! `nnpdf-lhgrid`. The other layouts are separate formats, not imported yet: MRST and MSTW grids
+//! rely on knots fixed in the LHAPDF5 readers, CTEQ grids embed one CTEQ table per member, and
+//! HERAPDF sets ship fit parameters evolved at runtime. Their files are rejected.
+//!
+//! The NNPDF layout is:
+//!
+//! ```text
+//! 'NNPDF20int'
+//! replicas, default member
+//! nx, followed by the x knots
+//! nq2, followed by the Q2 knots
+//! x f(x, Q2), for each member, x, Q2, and flavor from -6 to 6 (and possibly the photon)
+//! 'End:'
+//! ```
+//!
+//! The strong coupling is not imported.
+use anyhow::{anyhow, bail, Result};
+use ndarray::{Array1, Array3};
+
+use super::cache::file::{INFO_NAME, MEMBER_PATTERN, MEMBER_PLACEHOLDER};
+use super::fortran::{float, tokens, Records};
+use crate::block::Block;
+use crate::info::{Info, PID};
+use crate::member::{Member, Metadata, PDF_TYPE_KEY};
+use crate::uncertainty::ErrorType;
+
+const SECTIONS: [&str; 7] = [
+    "Description:",
+    "Alphas:",
+    "MinMax:",
+    "QCDparams:",
+    "Parameterlist:",
+    "Evolution:",
+    "End:",
+];
+
+/// Content of the leading quoted string of a `line`, if any.
+fn quoted(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix('\'')?.split('\'').next()
+}
+
+/// Members on the interpolated NNPDF grid.
+fn nnpdf(records: &mut Records) -> Result<Vec<Member>> {
+    let replicas = records.count()?;
+    let nx = records.count()?;
+    let xgrid = records.numbers(nx)?;
+    let nq2 = records.count()?;
+    let q2grid = records.numbers(nq2)?;
+
+    let mut values = Vec::new();
+    loop {
+        let line = records.text()?;
+        if quoted(line) == Some("End:") {
+            break;
+        }
+        for token in tokens(line) {
+            values.push(
+                float(token)
+                    .ok_or_else(|| anyhow!("Line {}: invalid number '{token}'", records.line()))?,
+            );
+        }
+    }
+
+    // the central member and the photon are not always present
+    let points = nx * nq2;
+    let candidates: Vec<_> = [
+        (replicas + 1, 13),
+        (replicas + 1, 14),
+        (replicas, 13),
+        (replicas, 14),
+    ]
+    .into_iter()
+    .filter(|(m, f)| m * points * f == values.len())
+    .collect();
+    let (members, flavors) = match candidates[..] {
+        [candidate] => candidate,
+        [] => bail!(
+            "{} values not matching {replicas} replicas on {nx} x {nq2} knots",
+            values.len()
+        ),
+        _ => bail!(
+            "{} values ambiguous for {replicas} replicas on {nx} x {nq2} knots, with or without \
+            the central member and the photon",
+            values.len()
+        ),
+    };
+
+    let mut pids: Vec<_> = (-6..=6).map(|p| if p == 0 { 21 } else { p }).collect();
+    if flavors == 14 {
+        pids.push(22);
+    }
+    let pids = Array1::from_vec(pids);
+    let xgrid = Array1::from_vec(xgrid);
+    let q2grid = Array1::from_vec(q2grid);
+
+    let mut grids: Vec<_> = values
+        .chunks_exact(points * flavors)
+        .map(|chunk| Member {
+            metadata: Metadata::from([(PDF_TYPE_KEY.to_owned(), "replica".to_owned())]),
+            blocks: vec![Block::new(
+                pids.clone(),
+                xgrid.clone(),
+                q2grid.clone(),
+                Array3::from_shape_fn((flavors, nx, nq2), |(p, i, j)| {
+                    chunk[(i * nq2 + j) * flavors + p]
+                }),
+            )],
+            interpolator: None,
+            extrapolator: None,
+        })
+        .collect();
+    if members == replicas {
+        let central = Member::average(&grids.iter().collect::<Vec<_>>())?;
+        grids.insert(0, central);
+    }
+    grids[0]
+        .metadata
+        .insert(PDF_TYPE_KEY.to_owned(), "central".to_owned());
+
+    Ok(grids)
+}
+
+/// Parse a whole grid file, returning the set metadata and its members.
+pub(crate) fn parse(content: &str) -> Result<(Info, Vec<Member>)> {
+    let mut records = Records::new(content);
+    if quoted(records.text()?) != Some("Version") {
+        bail!("Not an LHgrid file, the version is missing");
+    }
+
+    let mut info = Info::default();
+    let mut description = Vec::new();
+    let mut section = "";
+    let members = loop {
+        let line = records.text()?;
+        match quoted(line) {
+            Some(tag) if SECTIONS.contains(&tag) => section = tag,
+            _ if section == "Description:" => {
+                description.push(line.trim().trim_matches('\'').trim().to_owned());
+            }
+            _ => (),
+        }
+
+        match section {
+            "Evolution:" => {
+                let order = quoted(records.text()?).unwrap_or_default();
+                info.order.0 = match order.to_lowercase().as_str() {
+                    "lo" => 0,
+                    "nlo" => 1,
+                    "nnlo" => 2,
+                    _ => bail!("Line {}: unknown order '{order}'", records.line()),
+                };
+                let code = quoted(records.text()?).unwrap_or_default();
+                if !code.starts_with("NNPDF") {
+                    bail!(
+                        "LHgrid evolution '{code}' not supported by the nnpdf-lhgrid format, \
+                        only NNPDF grids are"
+                    );
+                }
+                break nnpdf(&mut records)?;
+            }
+            "End:" => bail!("Line {}: no grid found", records.line()),
+            _ => (),
+        }
+    };
+
+    let block = &members[0].blocks[0];
+    info.description = description.join(" ");
+    info.error_type = Some(ErrorType::Replicas.to_string());
+    info.num_members = Some(members.len() as u64);
+    info.flavors = block.pids.iter().map(|p| PID::from(*p)).collect();
+    info.x_min = block.xgrid.first().copied();
+    info.x_max = block.xgrid.last().copied();
+    info.q_min = block.mu2grid.first().map(|mu2| mu2.sqrt());
+    info.q_max = block.mu2grid.last().map(|mu2| mu2.sqrt());
+
+    Ok((info, members))
+}
+
+/// Convert a grid file, writing the metadata and all the members.
+pub(crate) fn unpack(
+    content: &[u8],
+    write: &mut impl FnMut(&str, &[u8]) -> Result<()>,
+) -> Result<()> {
+    let (info, members) = parse(std::str::from_utf8(content)?)?;
+
+    write(INFO_NAME, serde_yaml::to_string(&info)?.as_bytes())?;
+    for (num, member) in members.iter().enumerate() {
+        write(
+            &MEMBER_PATTERN.replace(MEMBER_PLACEHOLDER, &format!("{num:0>6}")),
+            &member.dump()?,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fmt::Write;
+
+    fn f(num: usize, pid: i32, x: f64, q2: f64) -> f64 {
+        (num + 1) as f64 * f64::from(pid) * x * q2
+    }
+
+    fn grid(members: usize, replicas: usize) -> String {
+        let (xs, q2s) = ([1e-3, 0.1, 1.], [2., 1e4]);
+        let mut content =
+            " 'Version' '5.8'\n 'Description:'\n 'Toy NNPDF set,'\n 'for tests'\n".to_owned();
+        content += " 'Alphas:'\n 'Variable', 'nnlo', 'EvolCode'\n 0.118, 91.1876\n";
+        content += " 'MinMax:'\n 2, 1\n 1e-3, 1.0, 2.0, 1e4\n";
+        content += " 'Evolution:'\n 'nnlo', 2.0, 1\n 'NNPDF20int'\n";
+        writeln!(content, " {replicas}, 1\n 3").unwrap();
+        for x in xs {
+            writeln!(content, " {x:E}").unwrap();
+        }
+        content += " 2\n";
+        for q2 in q2s {
+            writeln!(content, " {q2:E}").unwrap();
+        }
+        for num in 0..members {
+            for x in xs {
+                for q2 in q2s {
+                    for pid in -6..=6 {
+                        write!(content, " {:E}", f(num, pid, x, q2)).unwrap();
+                    }
+                    content += "\n";
+                }
+            }
+        }
+        content + " 'End:'\n"
+    }
+
+    #[test]
+    fn parse() {
+        let (info, members) = super::parse(&grid(3, 2)).unwrap();
+        assert_eq!(info.description, "Toy NNPDF set, for tests");
+        assert_eq!(info.order, (2, 0));
+        assert_eq!(info.num_members, Some(3));
+        assert_eq!(info.flavors.len(), 13);
+        assert_eq!((info.x_min, info.q_max), (Some(1e-3), Some(100.)));
+
+        let block = &members[2].blocks[0];
+        assert_eq!(block.pids[6], 21);
+        assert_eq!(block.values[(8, 1, 1)], f(2, 2, 0.1, 1e4));
+        assert_eq!(members[1].metadata[PDF_TYPE_KEY], "replica");
+
+        // the central member is computed, if missing
+        let (_, members) = super::parse(&grid(2, 2)).unwrap();
+        assert_eq!(members.len(), 3);
+        assert_eq!(
+            members[0].blocks[0].values[(8, 1, 1)],
+            1.5 * f(0, 2, 0.1, 1e4)
+        );
+        assert!(super::parse(&grid(1, 2)).is_err());
+        let other = grid(3, 2).replace("NNPDF20int", "MSTWgrid");
+        assert!(super::parse(&other).is_err());
+        // 14 members of 13 flavors, or 13 replicas with the photon
+        let ambiguous = super::parse(&grid(14, 13)).unwrap_err();
+        assert!(ambiguous.to_string().contains("ambiguous"));
+    }
+}
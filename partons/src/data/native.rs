@@ -0,0 +1,247 @@
+//! Native file format
+//!
+//! Native files start with a fixed size header, followed by the encoded payload:
+//!
+//! ```text
+//! | magic (4 bytes) | version (u16, LE) | CRC32 of the payload (u32, LE) | payload |
+//! ```
+//!
+//! Since version `2`, the payload is compressed in the LZ4 frame format. Files written before the
+//! header was introduced are plain payloads, and they are identified as version `0`.
+//!
+//! Native sources distribute each set as a tar archive, compressed in the LZ4 frame format,
+//! containing the files already in the cache layout.
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use flate2::Crc;
+use lazy_static::lazy_static;
+use lz4_flex::frame::FrameEncoder;
+use regex::Regex;
+use thiserror::Error;
+
+use super::cache::file::{INFO_NAME, MEMBER_PATTERN, MEMBER_PLACEHOLDER};
+use super::resource::Data;
+use crate::info::Info;
+use crate::set::Set;
+
+/// Magic number identifying native files
+pub(crate) const MAGIC: &[u8; 4] = b"PRTN";
+/// Current version of the payload schema, i.e. the bincode payload compressed in the LZ4 frame
+/// format
+pub(crate) const VERSION: u16 = 2;
+/// Version of the files with header and uncompressed bincode payload, written before the
+/// compression was introduced
+pub(crate) const UNCOMPRESSED_VERSION: u16 = 1;
+/// Version of the files without header, i.e. plain uncompressed bincode payloads
+pub(crate) const LEGACY_VERSION: u16 = 0;
+
+const HEADER_LEN: usize = MAGIC.len() + 2 + 4;
+
+/// Error reading a native file
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum NativeError {
+    /// Written by a more recent version of partons
+    #[error("Native format version {0} not supported, the latest known is {VERSION}")]
+    UnsupportedVersion(u16),
+    /// Content not matching its checksum
+    #[error("Corrupted content, checksum {found:#010x} instead of {expected:#010x}")]
+    Corrupted {
+        /// Checksum in the header
+        expected: u32,
+        /// Checksum of the content
+        found: u32,
+    },
+    /// Header shorter than expected
+    #[error("Truncated header")]
+    Truncated,
+}
+
+fn checksum(payload: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(payload);
+    crc.sum()
+}
+
+/// Prepend the header to the `payload`.
+pub(crate) fn encode(payload: &[u8]) -> Bytes {
+    let mut content = Vec::with_capacity(HEADER_LEN + payload.len());
+    content.extend_from_slice(MAGIC);
+    content.extend_from_slice(&VERSION.to_le_bytes());
+    content.extend_from_slice(&checksum(payload).to_le_bytes());
+    content.extend_from_slice(payload);
+    content.into()
+}
+
+/// Validate the header, and return the schema version together with the payload.
+///
+/// Content without the magic number is considered a legacy payload.
+pub(crate) fn decode(content: Bytes) -> Result<(u16, Bytes), NativeError> {
+    if !content.starts_with(MAGIC) {
+        return Ok((LEGACY_VERSION, content));
+    }
+    if content.len() < HEADER_LEN {
+        return Err(NativeError::Truncated);
+    }
+
+    let version = u16::from_le_bytes([content[4], content[5]]);
+    if version > VERSION {
+        return Err(NativeError::UnsupportedVersion(version));
+    }
+    let expected = u32::from_le_bytes([content[6], content[7], content[8], content[9]]);
+    let payload = content.slice(HEADER_LEN..);
+    let found = checksum(&payload);
+    if found != expected {
+        return Err(NativeError::Corrupted { expected, found });
+    }
+
+    Ok((version, payload))
+}
+
+lazy_static! {
+    static ref MEMBER_REGEX: Regex = Regex::new(r"^\d{6}\.member\.lz4$").unwrap();
+}
+
+/// Validate the content of a native archive.
+pub(crate) fn convert(bytes: Bytes, data: &Data) -> Result<Bytes> {
+    match data {
+        Data::Info(_) => {
+            Info::load(bytes.clone())?;
+        }
+        Data::Member(_, _) => {
+            decode(bytes.clone())?;
+        }
+        _ => (),
+    }
+    Ok(bytes)
+}
+
+/// Cache name of a file in a native archive, which has to be already in the cache layout.
+pub(crate) fn convert_name(path: PathBuf) -> Result<String> {
+    let Some(file_name) = path.file_name().and_then(|s| s.to_str()) else {
+        bail!("File name missing.");
+    };
+
+    if file_name == INFO_NAME || MEMBER_REGEX.is_match(file_name) {
+        Ok(file_name.to_owned())
+    } else {
+        bail!("Unexpected file '{file_name}' in native archive")
+    }
+}
+
+/// Write a set as a native archive, within `path`.
+///
+/// The archive is named according to the default grids pattern, such that it can be directly
+/// hosted by a native source.
+pub(crate) fn archive(set: &mut Set, path: &Path) -> Result<PathBuf> {
+    let name = set.name();
+    fs::create_dir_all(path)?;
+    let location = path.join(format!("{name}.partons.lz4"));
+
+    let mut builder = tar::Builder::new(FrameEncoder::new(File::create(&location)?));
+    let mut append = |file_name: String, content: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, Path::new(&name).join(file_name), content)
+    };
+
+    append(
+        INFO_NAME.to_owned(),
+        serde_yaml::to_string(set.info()?)?.as_bytes(),
+    )?;
+    for num in 0..set.size()? {
+        let file_name = MEMBER_PATTERN.replace(MEMBER_PLACEHOLDER, &format!("{num:0>6}"));
+        append(file_name, &set.member(num)?.dump()?)?;
+    }
+    builder.into_inner()?.finish()?;
+
+    Ok(location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::builder::{MemberBuilder, SetBuilder};
+    use crate::data::cache::file::Cache;
+    use crate::data::format::Format;
+    use crate::data::resource::{Resource, State};
+    use crate::data::source::Source;
+    use crate::member::Member;
+
+    #[test]
+    fn header() {
+        let payload = b"some content";
+        let content = encode(payload);
+        assert_eq!(&content[..4], MAGIC);
+        assert_eq!(
+            decode(content.clone()).unwrap(),
+            (VERSION, payload[..].into())
+        );
+
+        let legacy = Bytes::from_static(payload);
+        assert_eq!(decode(legacy.clone()).unwrap(), (LEGACY_VERSION, legacy));
+
+        let mut corrupted = content.to_vec();
+        corrupted[HEADER_LEN] ^= 1;
+        assert!(matches!(
+            decode(corrupted.into()),
+            Err(NativeError::Corrupted { .. })
+        ));
+
+        let mut future = content.to_vec();
+        future[4] = 42;
+        assert_eq!(
+            decode(future.into()),
+            Err(NativeError::UnsupportedVersion(42))
+        );
+        assert_eq!(decode(content.slice(..6)), Err(NativeError::Truncated));
+    }
+
+    #[test]
+    fn archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_owned();
+        let mut source = Source::local("local");
+        source.register_cache(path.clone());
+        let member = MemberBuilder::new()
+            .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+            .build(|_, x, _| 1. - x)
+            .unwrap();
+        let info = Info {
+            description: "Toy".to_owned(),
+            ..Info::default()
+        };
+        let mut set = SetBuilder::new("toy", info)
+            .member(member)
+            .build(&source)
+            .unwrap();
+        let location = set.archive(&path).unwrap();
+        assert!(location.ends_with("toy.partons.lz4"));
+
+        // unpack as a freshly downloaded archive, in a different cache
+        let cache = Cache::new("remote", path);
+        let original = |data| Resource {
+            data,
+            state: State::Original,
+        };
+        let content = Bytes::from(fs::read(location).unwrap());
+        let resource = original(Data::Set("toy".to_owned()));
+        cache.write(&resource, &content).unwrap();
+        cache.unpack(&resource, &Format::Native, content).unwrap();
+
+        let data = Data::Member("toy".to_owned(), 0);
+        let content = cache.read(&original(data.clone())).unwrap();
+        let member = Member::load(Format::Native.convert(content, &data).unwrap()).unwrap();
+        assert_eq!(member.blocks[0].values[(0, 0, 0)], 1. - 1e-3);
+        let data = Data::Info("toy".to_owned());
+        let content = cache.read(&original(data.clone())).unwrap();
+        assert_eq!(Info::load(content).unwrap().num_members, Some(1));
+
+        assert!(convert_name(PathBuf::from("toy/toy_0000.dat")).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! Import CTEQ tables
+//!
+//! CTEQ and CT sets are distributed as one `.pds` table per member, named `<set>.<member>.pds`.
+//! Each table starts with a textual header, containing the perturbative order, the strong
+//! coupling (as `\alpha_s` at a reference scale in recent tables, or as `\Lambda_QCD` in older
+//! ones), and the quark masses, followed by the grids and the values.
+//!
+//! Values are tabulated for the partons from `-NfMx` to `MxVal` in the CTEQ numbering, in which
+//! `1` is the up quark and `2` the down one, while the quarks beyond `MxVal` are equal to their
+//! antiquarks. Tables contain number densities, and a node at `x = 0`, which is dropped.
+//! Tables with the `IMASS` header, written since CT12, also tabulate `\alpha_s` on the Q nodes,
+//! which is imported for interpolation.
+//!
+//! Sets are distributed as gzipped tar archives of all the member tables.
+use std::io::Read;
+
+use anyhow::{anyhow, bail, Context, Result};
+use lazy_static::lazy_static;
+use ndarray::{Array1, Array3};
+use regex::Regex;
+
+use super::cache::file::{INFO_NAME, MEMBER_PATTERN, MEMBER_PLACEHOLDER};
+use super::format::entries;
+use super::fortran::{to_count, Records};
+use crate::block::Block;
+use crate::info::{Info, PID};
+use crate::member::{Member, Metadata, PDF_TYPE_KEY};
+
+lazy_static! {
+    static ref MEMBER_REGEX: Regex = Regex::new(r"(?i)\.(\d+)\.pds$").unwrap();
+}
+
+fn counts(numbers: &[f64], line: usize) -> Result<Vec<usize>> {
+    numbers
+        .iter()
+        .map(|n| to_count(*n).ok_or_else(|| anyhow!("Line {line}: invalid count {n}")))
+        .collect()
+}
+
+/// PDG identifier of a parton in the CTEQ numbering.
+fn pdg(ip: i32) -> i32 {
+    match ip {
+        0 => 21,
+        1 | -1 => 2 * ip,
+        2 | -2 => ip / 2,
+        _ => ip,
+    }
+}
+
+/// Parse a single table, returning the metadata it contains and the member.
+pub(crate) fn parse(content: &str) -> Result<(Info, Member)> {
+    let mut records = Records::new(content);
+    let mut info = Info {
+        description: records.text()?.trim().to_owned(),
+        ..Info::default()
+    };
+
+    let recent = records
+        .text()?
+        .trim_start()
+        .to_lowercase()
+        .starts_with("ipk");
+    let (order, masses, flavors, imass) = if recent {
+        // ipk, order, Q_alpha, alpha_s(Q_alpha), masses
+        let header = records.numbers(10)?;
+        info.mz = Some(header[2]);
+        info.alphas_mz = Some(header[3]);
+        info.alphas_type = Some("ode".to_owned());
+        let imass = records
+            .text()?
+            .trim_start()
+            .to_lowercase()
+            .starts_with("imass");
+        let flavors = if imass {
+            records.numbers(7)?[5..].to_vec()
+        } else {
+            records.numbers(5)?[3..].to_vec()
+        };
+        (header[1], header[4..].to_vec(), flavors, imass)
+    } else {
+        // order, number of flavors of Lambda, Lambda, masses
+        let header = records.numbers(9)?;
+        let lambda = Some(header[2]);
+        match to_count(header[1]) {
+            Some(3) => info.alphas_lambda3 = lambda,
+            Some(4) => info.alphas_lambda4 = lambda,
+            Some(5) => info.alphas_lambda5 = lambda,
+            Some(6) => info.alphas_lambda6 = lambda,
+            _ => bail!("Line {}: invalid number of flavors", records.line()),
+        }
+        info.alphas_type = Some("analytic".to_owned());
+        records.text()?;
+        (
+            header[0],
+            header[3..].to_vec(),
+            records.numbers(6)?[3..5].to_vec(),
+            false,
+        )
+    };
+    let flavors = counts(&flavors, records.line())?;
+    let (nfmx, mxval) = (flavors[0], flavors[1]);
+    if nfmx == 0 || nfmx > 6 || mxval > nfmx {
+        bail!("Line {}: invalid flavors {nfmx}, {mxval}", records.line());
+    }
+
+    let order = to_count(order)
+        .and_then(|o| o.checked_sub(1))
+        .ok_or_else(|| anyhow!("Invalid perturbative order {order}"))?;
+    info.order = (order as u64, 0);
+    info.alphas_order_qcd = Some(order as u64);
+    info.num_flavors = Some(nfmx as u64);
+    // quark masses, in the CTEQ order
+    info.m_up = Some(masses[0]);
+    info.m_down = Some(masses[1]);
+    info.m_strange = Some(masses[2]);
+    info.m_charm = Some(masses[3]);
+    info.m_bottom = Some(masses[4]);
+    info.m_top = Some(masses[5]);
+
+    records.text()?;
+    let sizes = records.numbers(5)?;
+    let sizes = counts(&[sizes[0], sizes[1], sizes[3]], records.line())?;
+    let (nx, nt, ng) = (sizes[0], sizes[1], sizes[2]);
+    if ng > 0 {
+        for _ in 0..=ng {
+            records.text()?;
+        }
+    }
+    records.text()?;
+    // Q_ini, Q_max, and then Q and its evolution variable, followed by `\alpha_s(Q)` in the
+    // tables with the IMASS header
+    let width = if imass { 3 } else { 2 };
+    let knots = records.numbers(2 + width * (nt + 1))?;
+    let column = |c: usize| -> Vec<_> { knots[2 + c..].iter().step_by(width).copied().collect() };
+    let qgrid = column(0);
+    if imass {
+        info.alphas_type = Some("ipol".to_owned());
+        info.alphas_qs = qgrid.clone();
+        info.alphas_vals = column(2);
+    }
+    records.text()?;
+    // x_min, the dummy node at `x = 0`, and the proper nodes
+    let xgrid = records.numbers(2 + nx)?[2..].to_vec();
+    records.text()?;
+    let table = records.numbers((nx + 1) * (nt + 1) * (nfmx + 1 + mxval))?;
+
+    if xgrid.len() < 2 || xgrid[0] <= 0. || xgrid.windows(2).any(|w| w[0] >= w[1]) {
+        bail!("The x nodes have to be positive and strictly increasing");
+    }
+    if qgrid.len() < 2 || qgrid[0] <= 0. || qgrid.windows(2).any(|w| w[0] >= w[1]) {
+        bail!("The Q nodes have to be positive and strictly increasing");
+    }
+
+    let nfmx = i32::try_from(nfmx)?;
+    let mxval = i32::try_from(mxval)?;
+    let pids: Vec<_> = (-nfmx..=nfmx).map(pdg).collect();
+    let values = Array3::from_shape_fn((pids.len(), nx, nt + 1), |(p, i, j)| {
+        let ip = i32::try_from(p).unwrap() - nfmx;
+        let ip = if ip > mxval { -ip } else { ip };
+        let offset = (ip + nfmx) as usize;
+        xgrid[i] * table[(offset * (nt + 1) + j) * (nx + 1) + i + 1]
+    });
+
+    info.flavors = pids.iter().map(|p| PID::from(*p)).collect();
+    info.x_min = xgrid.first().copied();
+    info.x_max = xgrid.last().copied();
+    info.q_min = qgrid.first().copied();
+    info.q_max = qgrid.last().copied();
+    info.error_type = Some("hessian".to_owned());
+    // CTEQ and CT eigenvectors are given at 90% confidence level
+    info.error_conf_level = Some(90.);
+
+    let block = Block::new(
+        Array1::from_vec(pids),
+        Array1::from_vec(xgrid),
+        Array1::from_vec(qgrid).mapv(|q| q * q),
+        values,
+    );
+    let member = Member {
+        metadata: Metadata::new(),
+        blocks: vec![block],
+        interpolator: None,
+        extrapolator: None,
+    };
+
+    Ok((info, member))
+}
+
+/// Convert a single table, named `file_name`, returning its member number and metadata.
+fn table(
+    file_name: &str,
+    content: &[u8],
+    write: &mut impl FnMut(&str, &[u8]) -> Result<()>,
+) -> Result<(u32, Info)> {
+    let Some(captures) = MEMBER_REGEX.captures(file_name) else {
+        bail!("Unexpected file '{file_name}' in PDS archive")
+    };
+    let num: u32 = captures[1].parse()?;
+
+    let (info, mut member) = parse(std::str::from_utf8(content)?)
+        .with_context(|| format!("Failed to parse {file_name}"))?;
+    let kind = if num == 0 { "central" } else { "error" };
+    member
+        .metadata
+        .insert(PDF_TYPE_KEY.to_owned(), kind.to_owned());
+
+    write(
+        &MEMBER_PATTERN.replace(MEMBER_PLACEHOLDER, &format!("{num:0>6}")),
+        &member.dump()?,
+    )?;
+    Ok((num, info))
+}
+
+/// Convert all the tables in a tar `archive`, writing the members and then the metadata.
+///
+/// The metadata are taken from the central member, and completed with the number of members.
+pub(crate) fn unpack(
+    archive: impl Read,
+    write: &mut impl FnMut(&str, &[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut central = None;
+    let mut members = 0;
+    entries(archive, |path, content| {
+        let Some(file_name) = path.file_name().and_then(|s| s.to_str()) else {
+            bail!("File name missing.");
+        };
+        let (num, info) = table(file_name, content, write)?;
+        if num == 0 {
+            central = Some(info);
+        }
+        members += 1;
+        Ok(())
+    })?;
+
+    let Some(mut info) = central else {
+        bail!("Central member missing from PDS archive");
+    };
+    info.num_members = Some(members);
+    write(INFO_NAME, serde_yaml::to_string(&info)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::fmt::Write;
+    use std::path::Path;
+
+    /// Tabulated density of the CTEQ parton `ip`
+    fn f(ip: i32, x: f64, q: f64) -> f64 {
+        f64::from(ip + 10) * (1. - x) * q
+    }
+
+    /// Tabulated strong coupling
+    fn alphas(q: f64) -> f64 {
+        0.3 / q.ln().max(1.)
+    }
+
+    /// Table with the old flavors header, or with the `IMASS` one and `\alpha_s` values.
+    fn table(imass: bool) -> String {
+        let (xs, qs) = ([0., 0.01, 0.1, 1.], [1.3_f64, 10., 100.]);
+        let mut content = "CT toy table\n  ipk, Ordr, Qalfa, AlfaQ, Mass 1-6\n".to_owned();
+        content += " 1, 2, 91.188, 0.118, 0, 0, 0.1, 1.3, 4.75, 172.\n";
+        if imass {
+            content += "  IMASS, aimass, fswitch, N0, N0, N0, Nfmx, MxVal\n";
+            content += " 1, 1.3, 0, 0, 0, 3, 2\n";
+        } else {
+            content += "  Iorder, Nfmx, MxVal\n 0, 0, 0, 3, 2\n";
+        }
+        content += "  NX,  NT, NfMx, NG, Nfl\n 3, 2, 3, 0, 0\n";
+        content += "  QINI, QMAX, (QV(I), TV(I), AlsCTEQ(I))\n 1.3, 100.,\n";
+        for q in qs {
+            write!(content, " {q}, {}", q.ln().ln()).unwrap();
+            if imass {
+                write!(content, ", {}", alphas(q)).unwrap();
+            }
+            content += "\n";
+        }
+        content += "  XMIN, XV(0:NX)\n 0.01, 0.\n 0.01, 0.1, 1.\n  Values\n";
+        for ip in -3..=2 {
+            for q in qs {
+                for x in xs {
+                    write!(content, " {:.10E}", f(ip, x, q)).unwrap();
+                }
+                content += "\n";
+            }
+        }
+        content
+    }
+
+    #[test]
+    fn parse() {
+        for imass in [false, true] {
+            let (info, member) = super::parse(&table(imass)).unwrap();
+            assert_eq!(info.description, "CT toy table");
+            assert_eq!(info.order, (1, 0));
+            assert_eq!((info.alphas_mz, info.m_bottom), (Some(0.118), Some(4.75)));
+            assert_eq!(info.flavors, vec![-3, -1, -2, 21, 2, 1, 3]);
+            assert_eq!((info.x_min, info.q_max), (Some(0.01), Some(100.)));
+
+            let block = &member.blocks[0];
+            assert_eq!(block.mu2grid.to_vec(), [1.3 * 1.3, 100., 10000.]);
+            // down quark, from the second CTEQ parton
+            assert!((block.values[(5, 1, 1)] - 0.1 * f(2, 0.1, 10.)).abs() < 1e-9);
+            // strange quark, beyond the valence ones
+            assert!((block.values[(6, 0, 2)] - 0.01 * f(-3, 0.01, 100.)).abs() < 1e-9);
+            assert_eq!(block.values[(3, 2, 0)], 0.);
+            assert_eq!(info.error_conf_level, Some(90.));
+        }
+
+        let (info, _) = super::parse(&table(true)).unwrap();
+        assert_eq!(info.alphas_type.as_deref(), Some("ipol"));
+        assert_eq!(info.alphas_qs, [1.3, 10., 100.]);
+        assert_eq!(info.alphas_vals, [alphas(1.3), alphas(10.), alphas(100.)]);
+        assert!(super::parse(&table(false))
+            .unwrap()
+            .0
+            .alphas_vals
+            .is_empty());
+        assert!(super::parse("CT toy table\n").is_err());
+    }
+
+    fn archive(files: &[&str]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for file in files {
+            let content = table(true);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, Path::new("toy").join(file), content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn unpack() {
+        let mut files = HashMap::new();
+        let mut write = |name: &str, content: &[u8]| {
+            files.insert(name.to_owned(), content.to_vec());
+            Ok(())
+        };
+        let tables = ["toy.00.pds", "toy.01.pds", "toy.02.pds"];
+        super::unpack(&archive(&tables)[..], &mut write).unwrap();
+
+        assert_eq!(files.len(), 4);
+        assert!(files.contains_key("000002.member.lz4"));
+        let info = Info::load(files[INFO_NAME].clone().into()).unwrap();
+        assert_eq!(info.num_members, Some(3));
+        assert_eq!(info.error_conf_level, Some(90.));
+
+        let mut ignore = |_: &str, _: &[u8]| Ok(());
+        assert!(super::unpack(&archive(&["toy.01.pds"])[..], &mut ignore).is_err());
+        assert!(super::unpack(&archive(&["README"])[..], &mut ignore).is_err());
+    }
+}
@@ -1,10 +1,13 @@
 use std::collections::HashMap;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use rayon::prelude::*;
 
 use super::header::Header;
-use super::resource::Data;
+use super::resource::{Data, Resource, State};
 use super::source::Source;
+use crate::info::Info;
 use crate::member::Member;
 use crate::set::Set;
 
@@ -14,11 +17,95 @@ impl Source {
         let remote = Self::replace_name(&self.patterns.grids, &header.name);
 
         self.load(remote.as_path(), Data::Set(header.name.to_owned()))?;
+        self.convert_members(header)?;
 
         Ok(Set {
             source: self.clone(),
             header: header.clone(),
             info: None,
+            mapping: None,
+            alphas: None,
+            members: HashMap::new(),
+        })
+    }
+
+    /// Find a set by name.
+    ///
+    /// The sets available in the cache are looked up first, including the ones written with
+    /// [`Source::store`], and then the remote index, if the source has any.
+    pub fn find(&self, name: &str) -> Result<Set> {
+        let cache = self.cache()?;
+        let stored = Resource {
+            data: Data::Set(name.to_owned()),
+            state: State::Regular,
+        };
+        if cache.exists(&stored) {
+            let members = cache.members(name, &State::Regular)?;
+            return Ok(Set {
+                source: self.clone(),
+                header: Header::new(0, name.to_owned(), u32::try_from(members.len())?),
+                info: None,
+                mapping: None,
+                alphas: None,
+                members: HashMap::new(),
+            });
+        }
+        if self.index.is_empty() {
+            bail!("No set {name} in source {}", self.name);
+        }
+
+        self.set(&self.index()?.get(name)?)
+    }
+
+    /// Convert all the unpacked members of the set, in parallel.
+    ///
+    /// All the members found in the original format are converted, except the ones already
+    /// available.
+    fn convert_members(&self, header: &Header) -> Result<()> {
+        let cache = self.cache()?;
+        let members = cache.members(&header.name, &State::Original)?;
+
+        members.into_par_iter().try_for_each(|num| {
+            let data = Data::Member(header.name.to_owned(), num);
+            let resource = |state| Resource {
+                data: data.clone(),
+                state,
+            };
+            let (original, regular) = (resource(State::Original), resource(State::Regular));
+            if cache.exists(&regular) {
+                return Ok(());
+            }
+
+            let content = self.format.convert(cache.read(&original)?, &data)?;
+            cache.write(&regular, &content)
+        })
+    }
+
+    /// Store a new set in the cache, with the given `name`.
+    ///
+    /// The set is then available as any other cached set, and it is returned ready to be used.
+    pub(crate) fn store(&self, name: &str, info: Info, members: &[Member]) -> Result<Set> {
+        let cache = self.cache()?;
+        let regular = |data| Resource {
+            data,
+            state: State::Regular,
+        };
+
+        // the regular set resource only marks the set as available
+        cache.write(&regular(Data::Set(name.to_owned())), &Bytes::new())?;
+        let content = serde_yaml::to_string(&info)?;
+        cache.write(&regular(Data::Info(name.to_owned())), &content.into())?;
+        for (num, member) in members.iter().enumerate() {
+            let data = Data::Member(name.to_owned(), u32::try_from(num)?);
+            cache.write(&regular(data), &member.dump()?)?;
+        }
+
+        Ok(Set {
+            source: self.clone(),
+            header: Header::new(0, name.to_owned(), u32::try_from(members.len())?),
+            info: Some(info),
+            mapping: None,
+            alphas: None,
             members: HashMap::new(),
         })
     }
@@ -39,3 +126,69 @@ impl Source {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::builder::MemberBuilder;
+
+    #[test]
+    fn convert_members() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut source = Source::local("remote");
+        source.register_cache(folder.path().to_owned());
+        let cache = source.cache().unwrap();
+
+        // only the originals, as unpacked from a freshly downloaded set
+        let resource = |num, state| Resource {
+            data: Data::Member("toy".to_owned(), num),
+            state,
+        };
+        for num in 0..3 {
+            let member = MemberBuilder::new()
+                .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+                .build(|_, x, _| f64::from(num) * (1. - x))
+                .unwrap();
+            cache
+                .write(&resource(num, State::Original), &member.dump().unwrap())
+                .unwrap();
+        }
+
+        // the index only records the set version
+        source
+            .convert_members(&Header::new(0, "toy".to_owned(), 1))
+            .unwrap();
+        assert_eq!(
+            cache.members("toy", &State::Regular).unwrap(),
+            vec![0, 1, 2]
+        );
+        let member = Member::load(cache.read(&resource(2, State::Regular)).unwrap()).unwrap();
+        assert_eq!(member.blocks[0].values[(0, 0, 0)], 2. * (1. - 1e-3));
+    }
+
+    #[test]
+    fn find() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut source = Source::local("local");
+        source.register_cache(folder.path().to_owned());
+
+        let member = MemberBuilder::new()
+            .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+            .build(|_, x, _| 1. - x)
+            .unwrap();
+        let info = Info {
+            description: "Toy".to_owned(),
+            ..Info::default()
+        };
+        source.store("toy", info, &[member]).unwrap();
+
+        let mut set = source.find("toy").unwrap();
+        assert_eq!(set.info().unwrap().description, "Toy");
+        assert_eq!(
+            set.member(0).unwrap().blocks[0].values[(0, 0, 0)],
+            1. - 1e-3
+        );
+        assert!(source.find("missing").is_err());
+    }
+}
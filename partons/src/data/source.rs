@@ -36,7 +36,7 @@ pub struct Source {
     url: String,
     pub(crate) index: String,
     #[serde(default)]
-    format: Format,
+    pub(crate) format: Format,
     #[serde(default)]
     pub(crate) patterns: Patterns,
     /// The data cache
@@ -75,7 +75,28 @@ impl Source {
         self.cache = Some(Cache::new(&self.name, data_path));
     }
 
-    fn cache(&self) -> Result<&Cache> {
+    /// A source without any remote, only holding the sets stored in its cache.
+    ///
+    /// It is meant for sets produced locally, e.g. with a
+    /// [`SetBuilder`](crate::builder::SetBuilder). The cache still has to be registered, with
+    /// [`Source::register_cache`].
+    pub fn local(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            url: String::new(),
+            index: String::new(),
+            format: Format::Native,
+            patterns: Patterns::default(),
+            cache: None,
+        }
+    }
+
+    /// Source name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn cache(&self) -> Result<&Cache> {
         self.cache.as_ref().ok_or(anyhow!("Cache not registered."))
     }
 
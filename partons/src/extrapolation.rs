@@ -0,0 +1,266 @@
+//! Extrapolation strategies
+//!
+//! They define the behavior outside the range covered by the block knots, and they reproduce the
+//! LHAPDF extrapolators, selected by name through the `Extrapolator` key of member headers and
+//! info files.
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::Result;
+use ndarray::Array1;
+use thiserror::Error;
+
+/// Error in the extrapolation
+///
+/// When raised during a member evaluation, it is wrapped in an [`anyhow::Error`], from which it
+/// can be recovered with [`anyhow::Error::downcast_ref`].
+#[derive(Error, Debug, PartialEq)]
+pub enum ExtrapolationError {
+    /// The extrapolator name is not recognized
+    #[error("Unknown extrapolator '{0}'")]
+    UnknownExtrapolator(String),
+    /// The momentum fraction is outside the allowed range
+    #[error("x = {value} outside the range [{min}, {max}]")]
+    XOutOfRange {
+        /// Requested value
+        value: f64,
+        /// Lower limit of the range
+        min: f64,
+        /// Upper limit of the range
+        max: f64,
+    },
+    /// The scale is outside the allowed range
+    #[error("mu2 = {value} outside the range [{min}, {max}]")]
+    Mu2OutOfRange {
+        /// Requested value
+        value: f64,
+        /// Lower limit of the range
+        min: f64,
+        /// Upper limit of the range
+        max: f64,
+    },
+}
+
+impl ExtrapolationError {
+    pub(crate) fn x(value: f64, knots: &Array1<f64>) -> Self {
+        Self::XOutOfRange {
+            value,
+            min: knots[0],
+            max: knots[knots.len() - 1],
+        }
+    }
+
+    pub(crate) fn mu2(value: f64, knots: &Array1<f64>) -> Self {
+        Self::Mu2OutOfRange {
+            value,
+            min: knots[0],
+            max: knots[knots.len() - 1],
+        }
+    }
+}
+
+/// Extrapolation strategy on a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Extrapolator {
+    /// Evaluate on the closest point of the grid boundary
+    Nearest,
+    /// Fail with an [`ExtrapolationError`]
+    Error,
+    /// Continue the interpolated function, the LHAPDF default
+    ///
+    /// Below the `x` range the function is continued log-linearly in `log(x)`, while above the
+    /// `mu2` range it is continued log-linearly in `log(mu2)`. Below the `mu2` range, it is
+    /// continued as a power law, with the anomalous dimension measured on the lowest knot.
+    /// Extrapolation above the `x` range is never allowed.
+    #[default]
+    Continuation,
+    /// Return zero
+    Zero,
+}
+
+impl FromStr for Extrapolator {
+    type Err = ExtrapolationError;
+
+    /// Parse the extrapolator name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nearest" => Ok(Self::Nearest),
+            "error" => Ok(Self::Error),
+            "continuation" => Ok(Self::Continuation),
+            "zero" => Ok(Self::Zero),
+            _ => Err(ExtrapolationError::UnknownExtrapolator(s.to_owned())),
+        }
+    }
+}
+
+impl Display for Extrapolator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Nearest => "nearest",
+            Self::Error => "error",
+            Self::Continuation => "continuation",
+            Self::Zero => "zero",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Check whether `value` is within the knots range.
+pub(crate) fn contains(knots: &Array1<f64>, value: f64) -> bool {
+    knots[0] <= value && value <= knots[knots.len() - 1]
+}
+
+/// Linear extrapolation of `(xl, yl)`, `(xh, yh)` to `x`.
+///
+/// If both values are sufficiently positive, `log(y)` is extrapolated instead, in order to keep
+/// the result positive.
+fn linear(x: f64, (xl, yl): (f64, f64), (xh, yh): (f64, f64)) -> f64 {
+    let t = (x - xl) / (xh - xl);
+    if yl > 1e-3 && yh > 1e-3 {
+        (yl.ln() + t * (yh.ln() - yl.ln())).exp()
+    } else {
+        yl + t * (yh - yl)
+    }
+}
+
+impl Extrapolator {
+    /// Extrapolate the function `interpolate`, defined on `xgrid` and `mu2grid`, to the point
+    /// `(x, mu2)`.
+    ///
+    /// The point is supposed to be outside the grid, and it is never interpolated directly.
+    pub(crate) fn extrapolate(
+        &self,
+        interpolate: impl Fn(f64, f64) -> Result<f64>,
+        xgrid: &Array1<f64>,
+        mu2grid: &Array1<f64>,
+        x: f64,
+        mu2: f64,
+    ) -> Result<f64> {
+        let (nx, nmu2) = (xgrid.len(), mu2grid.len());
+        let (xmin, xmax) = (xgrid[0], xgrid[nx - 1]);
+        let (mu2min, mu2max) = (mu2grid[0], mu2grid[nmu2 - 1]);
+
+        match self {
+            Self::Nearest => interpolate(x.clamp(xmin, xmax), mu2.clamp(mu2min, mu2max)),
+            Self::Error => {
+                if !contains(xgrid, x) {
+                    Err(ExtrapolationError::x(x, xgrid))?
+                }
+                Err(ExtrapolationError::mu2(mu2, mu2grid))?
+            }
+            Self::Zero => Ok(0.),
+            Self::Continuation => {
+                if x > xmax {
+                    Err(ExtrapolationError::x(x, xgrid))?
+                }
+
+                // continue in x, at fixed (and in-range) mu2
+                let low_x = |mu2| -> Result<f64> {
+                    if x >= xmin {
+                        return interpolate(x, mu2);
+                    }
+                    let (xmin1, lxmin, lxmin1) = (xgrid[1], xmin.ln(), xgrid[1].ln());
+                    Ok(linear(
+                        x.ln(),
+                        (lxmin, interpolate(xmin, mu2)?),
+                        (lxmin1, interpolate(xmin1, mu2)?),
+                    ))
+                };
+
+                if mu2 > mu2max {
+                    let mu2max1 = mu2grid[nmu2 - 2];
+                    Ok(linear(
+                        mu2.ln(),
+                        (mu2max.ln(), low_x(mu2max)?),
+                        (mu2max1.ln(), low_x(mu2max1)?),
+                    ))
+                } else if mu2 < mu2min {
+                    // anomalous dimension at the lowest scale, as in LHAPDF
+                    let fmin = low_x(mu2min)?;
+                    let fmin1 = low_x(1.01 * mu2min)?;
+                    let anomalous = if fmin > 1e-5 {
+                        ((fmin1 - fmin) / fmin / 0.01).max(-2.5)
+                    } else {
+                        1.
+                    };
+                    let r = mu2 / mu2min;
+                    Ok(fmin * r.powf(anomalous * r + 1. - r))
+                } else {
+                    low_x(mu2)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::Array;
+
+    fn extrapolate(extrapolator: Extrapolator, x: f64, mu2: f64) -> Result<f64> {
+        let xgrid = Array::geomspace(1e-5, 1., 30).unwrap();
+        let mu2grid = Array::geomspace(2., 1e5, 20).unwrap();
+        // a function log-linear in both variables
+        let f = |x: f64, mu2: f64| Ok(x.powf(-0.2) * mu2.powf(0.1));
+
+        extrapolator.extrapolate(f, &xgrid, &mu2grid, x, mu2)
+    }
+
+    #[test]
+    fn strategies() {
+        let nearest = extrapolate(Extrapolator::Nearest, 1e-7, 1e7).unwrap();
+        assert!((nearest - 1e-5_f64.powf(-0.2) * 1e5_f64.powf(0.1)).abs() < 1e-12);
+
+        assert_eq!(extrapolate(Extrapolator::Zero, 1e-7, 1e7).unwrap(), 0.);
+
+        let error = extrapolate(Extrapolator::Error, 0.1, 1e7).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ExtrapolationError>(),
+            Some(ExtrapolationError::Mu2OutOfRange { value, .. }) if *value == 1e7
+        ));
+    }
+
+    #[test]
+    fn continuation() {
+        // log-linear continuation is exact on log-linear functions
+        for (x, mu2) in [(1e-7, 10.), (0.1, 1e7), (1e-7, 1e7)] {
+            let value = extrapolate(Extrapolator::Continuation, x, mu2).unwrap();
+            let expected = x.powf(-0.2) * mu2.powf(0.1);
+            assert!((value - expected).abs() < 1e-10 * expected);
+        }
+
+        // power law at low scales, compared to LHAPDF
+        let value = extrapolate(Extrapolator::Continuation, 0.1, 1.).unwrap();
+        assert!((value - 1.160_389_546_662_922_6).abs() < 1e-12);
+        // with the anomalous dimension bounded from below
+        let xgrid = Array::geomspace(1e-5, 1., 30).unwrap();
+        let mu2grid = Array::geomspace(2., 1e5, 20).unwrap();
+        let steep = |_: f64, mu2: f64| Ok(mu2.powi(-5));
+        let value = Extrapolator::Continuation
+            .extrapolate(steep, &xgrid, &mu2grid, 0.1, 1.)
+            .unwrap();
+        assert!((value - 0.052_556_025_953_357_156).abs() < 1e-12);
+        // and not computed for small or negative values
+        let negative = |_: f64, mu2: f64| Ok(-mu2);
+        let value = Extrapolator::Continuation
+            .extrapolate(negative, &xgrid, &mu2grid, 0.1, 1.)
+            .unwrap();
+        assert!((value + 1.).abs() < 1e-12);
+
+        let error = extrapolate(Extrapolator::Continuation, 2., 10.).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ExtrapolationError>(),
+            Some(ExtrapolationError::XOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn names() {
+        for name in ["nearest", "error", "continuation", "zero"] {
+            assert_eq!(name.parse::<Extrapolator>().unwrap().to_string(), name);
+        }
+        assert!("linear".parse::<Extrapolator>().is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Conversion of Hessian sets to Monte Carlo replicas
+//!
+//! Each replica is a random gaussian combination of the eigenvector members, following Watt and
+//! Thorne (arXiv:1205.4024). For asymmetric Hessian sets, the member in the direction of the
+//! random displacement is used, with its absolute value.
+use anyhow::{bail, Result};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
+
+use crate::member::Member;
+use crate::set::Set;
+use crate::uncertainty::{quantile, ErrorInfo, ErrorType, CL_1_SIGMA};
+
+/// Coefficients of the central and PDF error members, for each of the `replicas`.
+///
+/// The eigenvector displacements are rescaled from the set confidence level `set_cl` to one
+/// standard deviation.
+pub(crate) fn coefficients(
+    error_info: &ErrorInfo,
+    set_cl: f64,
+    replicas: usize,
+    seed: u64,
+) -> Result<Vec<Vec<f64>>> {
+    let members = error_info.pdf_members().end;
+    let eigenvectors = match error_info.pdf {
+        ErrorType::SymmHessian => members - 1,
+        ErrorType::Hessian => (members - 1) / 2,
+        ErrorType::Replicas => bail!("Only Hessian sets can be converted to replicas"),
+    };
+    let scale = quantile(CL_1_SIGMA) / quantile(set_cl);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut all = Vec::with_capacity(replicas);
+    for _ in 0..replicas {
+        let mut coefficients = vec![0.; members];
+        for j in 0..eigenvectors {
+            let r: f64 = StandardNormal.sample(&mut rng);
+            let (member, displacement) = match error_info.pdf {
+                ErrorType::SymmHessian => (j + 1, r),
+                _ if r >= 0. => (2 * j + 1, r),
+                _ => (2 * j + 2, -r),
+            };
+            coefficients[member] += scale * displacement;
+        }
+        // displacements are relative to the central member
+        coefficients[0] = 1. - coefficients[1..].iter().sum::<f64>();
+        all.push(coefficients);
+    }
+
+    Ok(all)
+}
+
+impl Set {
+    /// Generate a Monte Carlo set of `replicas`, saved with the given `name`.
+    ///
+    /// The random sampling is fully determined by the `seed`. The central member of the new set
+    /// is the average of its replicas, while parameter variations are not propagated.
+    pub fn hessian2mc(&mut self, replicas: usize, seed: u64, name: &str) -> Result<Set> {
+        if replicas == 0 {
+            bail!("At least one replica is required");
+        }
+        let error_info = self.error_info()?;
+        let set_cl = self.info()?.error_conf_level.unwrap_or(CL_1_SIGMA);
+        let coefficients = coefficients(&error_info, set_cl, replicas, seed)?;
+
+        for num in 0..error_info.pdf_members().end {
+            self.member(u32::try_from(num)?)?;
+        }
+        let hessian: Vec<&Member> = (0..error_info.pdf_members().end)
+            .map(|num| &self.members[&(num as u32)])
+            .collect();
+
+        let members = coefficients
+            .iter()
+            .map(|replica| Member::combination(&hessian, replica))
+            .collect::<Result<Vec<_>>>()?;
+
+        let info = self.derived_info(&format!("Converted to {replicas} Monte Carlo replicas."))?;
+        self.store_replicas(name, info, members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling() {
+        let symm = ErrorInfo::new("symmhessian", 4).unwrap();
+        let sampled = coefficients(&symm, CL_1_SIGMA, 20000, 42).unwrap();
+        // reproducible
+        assert_eq!(
+            sampled[..5],
+            coefficients(&symm, CL_1_SIGMA, 5, 42).unwrap()[..]
+        );
+        // each eigenvector is sampled with unit variance
+        let n = sampled.len() as f64;
+        for j in 1..4 {
+            let variance = sampled.iter().map(|c| c[j] * c[j]).sum::<f64>() / n;
+            assert!((variance - 1.).abs() < 0.05);
+        }
+        assert!(sampled
+            .iter()
+            .all(|c| (c.iter().sum::<f64>() - 1.).abs() < 1e-12));
+
+        let asym = ErrorInfo::new("hessian+as", 7).unwrap();
+        for c in &coefficients(&asym, 90., 100, 1).unwrap() {
+            assert_eq!(c.len(), 5);
+            // only one direction per eigenvector, always with positive coefficients
+            assert!(c[1] * c[2] == 0. && c[3] * c[4] == 0.);
+            assert!(c[1..].iter().all(|v| *v >= 0.));
+        }
+
+        assert!(coefficients(&ErrorInfo::new("replicas", 3).unwrap(), 68., 1, 0).is_err());
+    }
+}
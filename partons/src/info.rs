@@ -10,11 +10,13 @@ use serde::{
 };
 use serde_yaml::Value;
 
+use crate::member::Member;
+
 /// Particle ID
 pub type PID = i64;
 
 /// Set metadata
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Info {
     /// Numerical identifier, within the source
     pub id: Option<u64>,
@@ -36,6 +38,61 @@ pub struct Info {
     pub data_version: Option<i64>,
     ///
     pub note: Option<String>,
+    /// Particle IDs of the flavors included
+    #[serde(default)]
+    pub flavors: Vec<PID>,
+    /// Number of members in the set
+    pub num_members: Option<u64>,
+    /// Lower limit of the `x` grid
+    pub x_min: Option<f64>,
+    /// Upper limit of the `x` grid
+    pub x_max: Option<f64>,
+    /// Lower limit of the `Q` grid, in GeV
+    pub q_min: Option<f64>,
+    /// Upper limit of the `Q` grid, in GeV
+    pub q_max: Option<f64>,
+    /// Confidence level of the uncertainties, in percent
+    pub error_conf_level: Option<f64>,
+    /// Flavor number scheme, e.g. `variable` or `fixed`
+    pub flavor_scheme: Option<String>,
+    /// Maximum number of active flavors
+    pub num_flavors: Option<u64>,
+    /// Mass of the Z boson, in GeV
+    pub mz: Option<f64>,
+    /// Mass of the up quark, in GeV
+    pub m_up: Option<f64>,
+    /// Mass of the down quark, in GeV
+    pub m_down: Option<f64>,
+    /// Mass of the strange quark, in GeV
+    pub m_strange: Option<f64>,
+    /// Mass of the charm quark, in GeV
+    pub m_charm: Option<f64>,
+    /// Mass of the bottom quark, in GeV
+    pub m_bottom: Option<f64>,
+    /// Mass of the top quark, in GeV
+    pub m_top: Option<f64>,
+    /// Value of `\alpha_s` at the Z mass
+    pub alphas_mz: Option<f64>,
+    /// Perturbative order of the `\alpha_s` running, `0` being leading order
+    pub alphas_order_qcd: Option<u64>,
+    /// Maximum number of active flavors in the `\alpha_s` running, if different from the set one
+    pub alphas_num_flavors: Option<u64>,
+    /// Kind of `\alpha_s` evaluator, one of `ipol`, `analytic`, and `ode`
+    pub alphas_type: Option<String>,
+    /// Scales on which `\alpha_s` is tabulated, in GeV
+    #[serde(default)]
+    pub alphas_qs: Vec<f64>,
+    /// Tabulated values of `\alpha_s`
+    #[serde(default)]
+    pub alphas_vals: Vec<f64>,
+    /// QCD scale, for 3 active flavors
+    pub alphas_lambda3: Option<f64>,
+    /// QCD scale, for 4 active flavors
+    pub alphas_lambda4: Option<f64>,
+    /// QCD scale, for 5 active flavors
+    pub alphas_lambda5: Option<f64>,
+    /// QCD scale, for 6 active flavors
+    pub alphas_lambda6: Option<f64>,
     /// Extra information to keep
     ///
     /// This field is here to store, mainly, legacy fields, that should be kept, but
@@ -47,6 +104,32 @@ impl Info {
     pub(crate) fn load(bytes: Bytes) -> Result<Self> {
         Ok(serde_yaml::from_slice(&bytes)?)
     }
+
+    /// Fill in the metadata derived from the set content.
+    ///
+    /// The number of members is always updated, while flavors and grid limits are only taken
+    /// from the `central` member grids if missing.
+    pub(crate) fn complete(&mut self, central: &Member, members: u32) {
+        let (first, last) = (central.blocks.first(), central.blocks.last());
+
+        self.num_members = Some(u64::from(members));
+        if self.flavors.is_empty() {
+            self.flavors =
+                first.map_or_else(Vec::new, |b| b.pids.iter().map(|p| PID::from(*p)).collect());
+        }
+        self.x_min = self
+            .x_min
+            .or_else(|| first.and_then(|b| b.xgrid.first().copied()));
+        self.x_max = self
+            .x_max
+            .or_else(|| first.and_then(|b| b.xgrid.last().copied()));
+        self.q_min = self
+            .q_min
+            .or_else(|| first.and_then(|b| b.mu2grid.first().map(|mu2| mu2.sqrt())));
+        self.q_max = self
+            .q_max
+            .or_else(|| last.and_then(|b| b.mu2grid.last().map(|mu2| mu2.sqrt())));
+    }
 }
 
 /// A set author
@@ -0,0 +1,308 @@
+//! Interpolation strategies
+//!
+//! The available strategies reproduce the LHAPDF interpolators, and they are selected by name
+//! through the `Interpolator` key of member headers and info files.
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::Result;
+use ndarray::{Array1, ArrayView2};
+use thiserror::Error;
+
+use crate::extrapolation::ExtrapolationError;
+
+/// Error in the interpolation configuration
+#[derive(Error, Debug)]
+pub enum InterpolationError {
+    /// The interpolator name is not recognized
+    #[error("Unknown interpolator '{0}'")]
+    UnknownInterpolator(String),
+}
+
+/// Interpolation strategy on a block
+///
+/// All the strategies are two-dimensional, in the `(x, mu2)` plane. The cubic ones estimate the
+/// derivatives at the knots by finite differences (central in the interior, one-sided on the
+/// edges), and they fall back on their linear counterpart when less than four `mu2` knots are
+/// available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolator {
+    /// Bilinear in `(x, mu2)`
+    Linear,
+    /// Bicubic in `(x, mu2)`
+    Cubic,
+    /// Bilinear in `(log(x), log(mu2))`
+    LogLinear,
+    /// Bicubic in `(log(x), log(mu2))`, the LHAPDF default
+    #[default]
+    LogBicubic,
+}
+
+impl FromStr for Interpolator {
+    type Err = InterpolationError;
+
+    /// Parse the interpolator name.
+    ///
+    /// Both the LHAPDF names (`linear`, `cubic`, `log`, `logcubic`) and the more explicit ones
+    /// (`bilinear`, `bicubic`, `loglinear`, `logbicubic`) are accepted, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" | "bilinear" => Ok(Self::Linear),
+            "cubic" | "bicubic" => Ok(Self::Cubic),
+            "log" | "loglinear" | "logbilinear" => Ok(Self::LogLinear),
+            "logcubic" | "logbicubic" => Ok(Self::LogBicubic),
+            _ => Err(InterpolationError::UnknownInterpolator(s.to_owned())),
+        }
+    }
+}
+
+impl Display for Interpolator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Linear => "linear",
+            Self::Cubic => "cubic",
+            Self::LogLinear => "log",
+            Self::LogBicubic => "logcubic",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Interpolator {
+    /// Interpolate `values`, sampled on `xgrid` and `mu2grid`, at the point `(x, mu2)`.
+    ///
+    /// Points outside the grid result in an [`ExtrapolationError`].
+    pub(crate) fn interpolate(
+        &self,
+        values: &ArrayView2<f64>,
+        xgrid: Knots,
+        mu2grid: Knots,
+        x: f64,
+        mu2: f64,
+    ) -> Result<f64> {
+        let ix = knot_below(xgrid.knots, x).ok_or_else(|| ExtrapolationError::x(x, xgrid.knots))?;
+        let imu = knot_below(mu2grid.knots, mu2)
+            .ok_or_else(|| ExtrapolationError::mu2(mu2, mu2grid.knots))?;
+
+        let log = matches!(self, Self::LogLinear | Self::LogBicubic);
+        let xs = xgrid.coordinates(log);
+        let mu2s = mu2grid.coordinates(log);
+        let point = if log { (x.ln(), mu2.ln()) } else { (x, mu2) };
+
+        let cubic = matches!(self, Self::Cubic | Self::LogBicubic);
+        if cubic && mu2s.len() >= 4 {
+            Ok(bicubic(values, xs, mu2s, (ix, imu), point))
+        } else {
+            Ok(bilinear(values, xs, mu2s, (ix, imu), point))
+        }
+    }
+}
+
+/// Interpolation knots along one dimension, together with their logarithms.
+#[derive(Clone, Copy)]
+pub(crate) struct Knots<'a> {
+    pub(crate) knots: &'a Array1<f64>,
+    pub(crate) logs: &'a Array1<f64>,
+}
+
+impl<'a> Knots<'a> {
+    fn coordinates(&self, log: bool) -> &'a Array1<f64> {
+        if log {
+            self.logs
+        } else {
+            self.knots
+        }
+    }
+}
+
+/// Locate the interval containing `value`, returning the index of its lower knot.
+///
+/// A value lying exactly on the last knot is attributed to the last interval, while `None` is
+/// returned for any value outside the knots range.
+pub(crate) fn knot_below(knots: &Array1<f64>, value: f64) -> Option<usize> {
+    let n = knots.len();
+    if n < 2 || !(knots[0] <= value && value <= knots[n - 1]) {
+        return None;
+    }
+    if value == knots[n - 1] {
+        return Some(n - 2);
+    }
+
+    // bisect, keeping `knots[low] <= value < knots[high]`
+    let (mut low, mut high) = (0, n - 1);
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if knots[mid] <= value {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(low)
+}
+
+/// Cubic Hermite polynomial on the unit interval.
+///
+/// `vl` and `vh` are the values on the edges, `vdl` and `vdh` the corresponding derivatives,
+/// already rescaled to the unit interval.
+pub(crate) fn hermite(t: f64, vl: f64, vdl: f64, vh: f64, vdh: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (2. * t3 - 3. * t2 + 1.) * vl
+        + (t3 - 2. * t2 + t) * vdl
+        + (-2. * t3 + 3. * t2) * vh
+        + (t3 - t2) * vdh
+}
+
+/// Finite difference derivative along `x` of the values on the knot `(ix, imu)`.
+fn ddx(values: &ArrayView2<f64>, xs: &Array1<f64>, ix: usize, imu: usize) -> f64 {
+    let last = xs.len() - 1;
+    let backward = || (values[[ix, imu]] - values[[ix - 1, imu]]) / (xs[ix] - xs[ix - 1]);
+    let forward = || (values[[ix + 1, imu]] - values[[ix, imu]]) / (xs[ix + 1] - xs[ix]);
+
+    match ix {
+        0 => forward(),
+        i if i == last => backward(),
+        _ => (backward() + forward()) / 2.,
+    }
+}
+
+/// Cubic interpolation along `x`, on the `mu2` knot `imu`.
+fn cubic_x(values: &ArrayView2<f64>, xs: &Array1<f64>, ix: usize, imu: usize, t: f64) -> f64 {
+    let dx = xs[ix + 1] - xs[ix];
+
+    hermite(
+        t,
+        values[[ix, imu]],
+        ddx(values, xs, ix, imu) * dx,
+        values[[ix + 1, imu]],
+        ddx(values, xs, ix + 1, imu) * dx,
+    )
+}
+
+fn bicubic(
+    values: &ArrayView2<f64>,
+    xs: &Array1<f64>,
+    mu2s: &Array1<f64>,
+    (ix, imu): (usize, usize),
+    (x, mu2): (f64, f64),
+) -> f64 {
+    let tx = (x - xs[ix]) / (xs[ix + 1] - xs[ix]);
+    let dmu2 = mu2s[imu + 1] - mu2s[imu];
+    let tmu2 = (mu2 - mu2s[imu]) / dmu2;
+
+    // values on the enclosing mu2 knots, and their neighbours
+    let vl = cubic_x(values, xs, ix, imu, tx);
+    let vh = cubic_x(values, xs, ix, imu + 1, tx);
+    let lower = || {
+        let vll = cubic_x(values, xs, ix, imu - 1, tx);
+        (vl - vll) / (mu2s[imu] - mu2s[imu - 1])
+    };
+    let upper = || {
+        let vhh = cubic_x(values, xs, ix, imu + 2, tx);
+        (vhh - vh) / (mu2s[imu + 2] - mu2s[imu + 1])
+    };
+    let central = (vh - vl) / dmu2;
+
+    // derivatives along mu2, with one-sided differences on the edges
+    let (vdl, vdh) = if imu == 0 {
+        (central, (central + upper()) / 2.)
+    } else if imu + 2 == mu2s.len() {
+        ((central + lower()) / 2., central)
+    } else {
+        ((central + lower()) / 2., (central + upper()) / 2.)
+    };
+
+    hermite(tmu2, vl, vdl * dmu2, vh, vdh * dmu2)
+}
+
+fn bilinear(
+    values: &ArrayView2<f64>,
+    xs: &Array1<f64>,
+    mu2s: &Array1<f64>,
+    (ix, imu): (usize, usize),
+    (x, mu2): (f64, f64),
+) -> f64 {
+    let tx = (x - xs[ix]) / (xs[ix + 1] - xs[ix]);
+    let tmu2 = (mu2 - mu2s[imu]) / (mu2s[imu + 1] - mu2s[imu]);
+    let linear = |imu: usize| values[[ix, imu]] + tx * (values[[ix + 1, imu]] - values[[ix, imu]]);
+
+    linear(imu) + tmu2 * (linear(imu + 1) - linear(imu))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::{Array, Array2};
+
+    fn grid(f: impl Fn(f64, f64) -> f64, nmu2: usize) -> (Array2<f64>, Array1<f64>, Array1<f64>) {
+        let xgrid = Array::geomspace(1e-5, 1., 30).unwrap();
+        let mu2grid = Array::geomspace(2., 1e5, nmu2).unwrap();
+        let values = Array2::from_shape_fn((xgrid.len(), mu2grid.len()), |(i, j)| {
+            f(xgrid[i], mu2grid[j])
+        });
+        (values, xgrid, mu2grid)
+    }
+
+    #[test]
+    fn names() {
+        for interpolator in [
+            Interpolator::Linear,
+            Interpolator::Cubic,
+            Interpolator::LogLinear,
+            Interpolator::LogBicubic,
+        ] {
+            let parsed: Interpolator = interpolator.to_string().parse().unwrap();
+            assert_eq!(parsed, interpolator);
+        }
+
+        assert_eq!(
+            "LogBicubic".parse::<Interpolator>().unwrap(),
+            Interpolator::LogBicubic
+        );
+        assert!("spline".parse::<Interpolator>().is_err());
+    }
+
+    #[test]
+    fn exact_bilinear() {
+        // each strategy reproduces exactly bilinear functions in its own coordinates
+        let linear = |x: f64, mu2: f64| 1. + 2. * x - 1e-4 * mu2 + 0.5 * x * mu2;
+        let log = |x: f64, mu2: f64| linear(x.ln(), mu2.ln());
+
+        let cases = [
+            (Interpolator::Linear, false),
+            (Interpolator::Cubic, false),
+            (Interpolator::LogLinear, true),
+            (Interpolator::LogBicubic, true),
+        ];
+        for (interpolator, logarithmic) in cases {
+            let f = |x, mu2| {
+                if logarithmic {
+                    log(x, mu2)
+                } else {
+                    linear(x, mu2)
+                }
+            };
+            for nmu2 in [3, 20] {
+                let (values, xgrid, mu2grid) = grid(f, nmu2);
+                let (logx, logmu2) = (xgrid.mapv(f64::ln), mu2grid.mapv(f64::ln));
+                let xs = Knots {
+                    knots: &xgrid,
+                    logs: &logx,
+                };
+                let mu2s = Knots {
+                    knots: &mu2grid,
+                    logs: &logmu2,
+                };
+                for (x, mu2) in [(3e-5, 2.5), (0.123, 1e3), (0.99, 9e4), (1., 1e5)] {
+                    let value = interpolator
+                        .interpolate(&values.view(), xs, mu2s, x, mu2)
+                        .unwrap();
+                    assert!((value - f(x, mu2)).abs() < 1e-8 * f(x, mu2).abs().max(1.));
+                }
+            }
+        }
+    }
+}
@@ -32,11 +32,23 @@
 #![warn(clippy::all, clippy::pedantic, clippy::restriction, clippy::cargo)]
 #![warn(missing_docs)]
 
+pub mod alphas;
 mod block;
+pub mod builder;
+pub mod combination;
+pub mod compression;
 pub mod configs;
+pub mod extrapolation;
+pub mod hessian2mc;
 pub mod info;
+pub mod interpolation;
+pub mod mc2hessian;
 pub mod member;
-mod quantity;
+pub mod metadata;
+pub mod quantity;
+pub mod reweighting;
+pub mod sampling;
 pub mod set;
+pub mod uncertainty;
 
 pub mod data;
@@ -0,0 +1,150 @@
+//! Conversion of Monte Carlo replicas to a Hessian set
+//!
+//! The replicas deviations from the central member, sampled on a grid of points, are decomposed
+//! in singular values, and the largest ones are used as symmetric eigenvectors (Carrazza et al.,
+//! arXiv:1505.06736). Each eigenvector is a linear combination of the replicas.
+use anyhow::{bail, Result};
+use nalgebra::DMatrix;
+
+use crate::member::{Member, PDF_TYPE_KEY};
+use crate::sampling::Sampling;
+use crate::set::Set;
+use crate::uncertainty::{ErrorType, CL_1_SIGMA};
+
+/// Accuracy of the covariance reproduction, on the sampling points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reproduction {
+    /// Fraction of the total variance captured by the eigenvectors
+    pub explained: f64,
+    /// Distance of the covariance matrices, relative to the original one, in Frobenius norm
+    pub covariance: f64,
+    /// Largest relative deviation of the standard deviations
+    pub errors: f64,
+}
+
+/// Eigenvectors coefficients, from the replicas `deviations` on the sampling points.
+///
+/// The columns of `deviations` are the replicas, and the coefficients of the first `eigenvectors`
+/// are returned, one column each, together with the accuracy they reach.
+pub(crate) fn decompose(
+    deviations: &DMatrix<f64>,
+    eigenvectors: usize,
+) -> Result<(DMatrix<f64>, Reproduction)> {
+    let (points, replicas) = deviations.shape();
+    if eigenvectors == 0 || eigenvectors > replicas.min(points) {
+        bail!(
+            "Number of eigenvectors has to be in [1, {}]",
+            replicas.min(points)
+        );
+    }
+
+    let svd = deviations.clone().svd(false, true);
+    let v_t = svd
+        .v_t
+        .ok_or_else(|| anyhow::anyhow!("Singular value decomposition failed"))?;
+    let mut order: Vec<usize> = (0..svd.singular_values.len()).collect();
+    order.sort_by(|a, b| svd.singular_values[*b].total_cmp(&svd.singular_values[*a]));
+
+    let norm = ((replicas - 1) as f64).sqrt();
+    let coefficients = DMatrix::from_fn(replicas, eigenvectors, |k, j| v_t[(order[j], k)] / norm);
+
+    let total: f64 = svd.singular_values.iter().map(|s| s * s).sum();
+    let kept: f64 = order[..eigenvectors]
+        .iter()
+        .map(|i| svd.singular_values[*i].powi(2))
+        .sum();
+
+    let original = deviations * deviations.transpose() / (norm * norm);
+    let hessian = deviations * &coefficients;
+    let reproduced = &hessian * hessian.transpose();
+    let errors = (0..points)
+        .filter(|i| original[(*i, *i)] > 0.)
+        .map(|i| ((reproduced[(i, i)] / original[(i, i)]).sqrt() - 1.).abs())
+        .fold(0., f64::max);
+
+    let reproduction = Reproduction {
+        explained: if total > 0. { kept / total } else { 1. },
+        covariance: (&original - &reproduced).norm() / original.norm(),
+        errors,
+    };
+    Ok((coefficients, reproduction))
+}
+
+impl Set {
+    /// Convert the replicas into a symmetric Hessian set with the given number of
+    /// `eigenvectors`, saved with the given `name`.
+    ///
+    /// The replicas are sampled on the `sampling` points, and the central member is preserved.
+    /// The new set is returned, together with the accuracy of the conversion on the sampling
+    /// points.
+    pub fn mc2hessian(
+        &mut self,
+        sampling: &Sampling,
+        eigenvectors: usize,
+        name: &str,
+    ) -> Result<(Set, Reproduction)> {
+        let error_info = self.error_info()?;
+        if error_info.pdf != ErrorType::Replicas {
+            bail!("Only replicas sets can be converted to Hessian");
+        }
+        let members = error_info.pdf_members().end;
+
+        let central = sampling.evaluate(self, 0)?;
+        let mut deviations = DMatrix::zeros(sampling.len(), members - 1);
+        for num in 1..members {
+            let values = sampling.evaluate(self, u32::try_from(num)?)? - &central;
+            deviations.set_column(num - 1, &values.iter().copied().collect::<Vec<_>>().into());
+        }
+
+        let (coefficients, reproduction) = decompose(&deviations, eigenvectors)?;
+
+        let replicas: Vec<&Member> = (0..members)
+            .map(|num| &self.members[&(num as u32)])
+            .collect();
+        let mut hessian = vec![replicas[0].clone()];
+        for column in coefficients.column_iter() {
+            let mut combination = vec![1. - column.sum()];
+            combination.extend(column.iter());
+            let mut member = Member::combination(&replicas, &combination)?;
+            member
+                .metadata
+                .insert(PDF_TYPE_KEY.to_owned(), "error".to_owned());
+            hessian.push(member);
+        }
+
+        let mut info = self.derived_info(&format!(
+            "Converted to {eigenvectors} symmetric Hessian eigenvectors."
+        ))?;
+        info.error_type = Some(ErrorType::SymmHessian.to_string());
+        info.error_conf_level = Some(CL_1_SIGMA);
+        info.num_members = Some(hessian.len() as u64);
+
+        Ok((self.source.store(name, info, &hessian)?, reproduction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decomposition() {
+        // replicas spanning a two dimensional space
+        let (points, replicas) = (10, 40);
+        let deviations = DMatrix::from_fn(points, replicas, |i, k| {
+            let (i, k) = (i as f64, k as f64);
+            (k * 0.7).sin() * i + (k * 1.3).cos() * (i * i - 4.)
+        });
+
+        let (coefficients, reproduction) = decompose(&deviations, 2).unwrap();
+        assert_eq!(coefficients.shape(), (replicas, 2));
+        assert!((reproduction.explained - 1.).abs() < 1e-12);
+        assert!(reproduction.covariance < 1e-12);
+        assert!(reproduction.errors < 1e-10);
+
+        let (_, partial) = decompose(&deviations, 1).unwrap();
+        assert!(partial.explained < 1. && partial.covariance > 1e-3);
+
+        assert!(decompose(&deviations, 11).is_err());
+    }
+}
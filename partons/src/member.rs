@@ -3,24 +3,45 @@
 use std::collections::HashMap;
 use std::fmt::{self, Display};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use bincode::{Decode, Encode};
-use bytes::Bytes;
-use ndarray::Array1;
-use serde::{Deserialize, Serialize};
+use bytes::{Buf, Bytes};
+use itertools::izip;
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+use ndarray::{Array1, Array3};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
 
 use crate::block::Block;
+use crate::data::native;
+use crate::extrapolation::{contains, Extrapolator};
+use crate::info::Info;
+use crate::interpolation::Interpolator;
+use crate::metadata;
 
 pub(crate) type Metadata = HashMap<String, String>;
 
+/// Metadata key selecting the interpolation strategy
+pub(crate) const INTERPOLATOR_KEY: &str = "Interpolator";
+/// Metadata key selecting the extrapolation strategy
+pub(crate) const EXTRAPOLATOR_KEY: &str = "Extrapolator";
+/// Metadata key describing the role of the member in the set
+pub(crate) const PDF_TYPE_KEY: &str = "PdfType";
+
 /// Member of a set
 ///
 /// This contains the whole member data, including the interpolation
 /// [`Block`](crate::block::Block)s and further optional metadata.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Member {
     pub(crate) metadata: Metadata,
     pub(crate) blocks: Vec<Block>,
+    /// Interpolator chosen at runtime, overriding the one in the metadata
+    #[serde(skip)]
+    pub(crate) interpolator: Option<Interpolator>,
+    /// Extrapolator chosen at runtime, overriding the one in the metadata
+    #[serde(skip)]
+    pub(crate) extrapolator: Option<Extrapolator>,
 }
 
 #[derive(Decode, Encode)]
@@ -30,26 +51,292 @@ pub(crate) struct MemberWrapper {
 }
 
 impl Member {
+    /// Decode a member in the native format.
+    ///
+    /// The payload is decompressed while decoding. Legacy members, not compressed and possibly
+    /// without header, are migrated transparently.
     pub(crate) fn load(bytes: Bytes) -> Result<Self> {
-        let decoded: MemberWrapper =
-            bincode::decode_from_slice(&bytes, bincode::config::standard())?.0;
+        let config = bincode::config::standard();
+        let (version, payload) = native::decode(bytes)?;
+        let decoded: MemberWrapper = match version {
+            native::LEGACY_VERSION | native::UNCOMPRESSED_VERSION => {
+                bincode::decode_from_slice(&payload, config)?.0
+            }
+            native::VERSION => {
+                let mut decoder = FrameDecoder::new(payload.reader());
+                bincode::decode_from_std_read(&mut decoder, config)?
+            }
+            _ => bail!(native::NativeError::UnsupportedVersion(version)),
+        };
         Ok(decoded.member)
     }
 
+    /// Encode the member in the native format, compressed with LZ4.
+    pub(crate) fn dump(&self) -> Result<Bytes> {
+        let mut encoder = FrameEncoder::new(Vec::new());
+        bincode::serde::encode_into_std_write(self, &mut encoder, bincode::config::standard())?;
+        Ok(native::encode(&encoder.finish()?))
+    }
+
+    /// Average of the values of `members`, sharing the same grids.
+    ///
+    /// The metadata are inherited from the first member.
+    pub(crate) fn average(members: &[&Member]) -> Result<Self> {
+        let weight = 1. / members.len() as f64;
+        Self::combination(members, &vec![weight; members.len()])
+    }
+
+    /// Linear combination of the values of `members`, sharing the same grids.
+    ///
+    /// The metadata are inherited from the first member.
+    pub(crate) fn combination(members: &[&Member], coefficients: &[f64]) -> Result<Self> {
+        let Some((first, others)) = members.split_first() else {
+            bail!("No member to combine");
+        };
+        if members.len() != coefficients.len() {
+            bail!("Each member requires exactly one coefficient");
+        }
+
+        let mut combination = (*first).clone();
+        for block in &mut combination.blocks {
+            block.values *= coefficients[0];
+        }
+        for (member, coefficient) in others.iter().zip(&coefficients[1..]) {
+            if member.blocks.len() != combination.blocks.len() {
+                bail!("Members with different number of blocks cannot be combined");
+            }
+            for (block, other) in combination.blocks.iter_mut().zip(&member.blocks) {
+                if block.pids != other.pids
+                    || block.xgrid != other.xgrid
+                    || block.mu2grid != other.mu2grid
+                {
+                    bail!("Members with different grids cannot be combined");
+                }
+                block.values.scaled_add(*coefficient, &other.values);
+            }
+        }
+
+        Ok(combination)
+    }
+
+    /// Interpolate the member on the grids of the `blocks`, replacing its own.
+    ///
+    /// Each knot is filled from the block covering its scale, see [`Member::block`]. Knots lying
+    /// on a threshold of the member are instead filled from the block covering the
+    /// central scale of their target block, such that matching flavor thresholds are preserved.
+    /// The metadata are kept, while the strategies are resolved within the `set` metadata, see
+    /// [`Member::strategies`].
+    pub(crate) fn resample(&self, blocks: &[Block], set: &Mapping) -> Result<Self> {
+        let (interpolator, extrapolator) = self.strategies(set)?;
+
+        let mut resampled = self.clone();
+        resampled.blocks = Vec::with_capacity(blocks.len());
+        for target in blocks {
+            let (Some(first), Some(last)) = (target.mu2grid.first(), target.mu2grid.last()) else {
+                bail!("Empty scale grid");
+            };
+            let central = self.block((first * last).sqrt())?;
+            let sources = target
+                .mu2grid
+                .iter()
+                .map(|&mu2| {
+                    // up to rounding, e.g. of generated knots
+                    let threshold = self.blocks[1..]
+                        .iter()
+                        .any(|b| (b.mu2grid[0] - mu2).abs() <= 1e-12 * mu2);
+                    if threshold && contains(&central.mu2grid, mu2) {
+                        Ok(central)
+                    } else {
+                        self.block(mu2)
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut values = Array3::zeros(target.values.raw_dim());
+            for ((p, i, j), value) in values.indexed_iter_mut() {
+                *value = sources[j].interp(
+                    target.pids[p],
+                    target.xgrid[i],
+                    target.mu2grid[j],
+                    &interpolator,
+                    &extrapolator,
+                )?;
+            }
+            resampled.blocks.push(Block::new(
+                target.pids.clone(),
+                target.xgrid.clone(),
+                target.mu2grid.clone(),
+                values,
+            ));
+        }
+
+        Ok(resampled)
+    }
+
+    /// Interpolation strategy.
+    ///
+    /// The strategy explicitly set with [`Member::set_interpolator`] has the precedence, otherwise
+    /// it is read from the `Interpolator` metadata key. If neither is available, the default
+    /// [`Interpolator::LogBicubic`] is used.
+    pub fn interpolator(&self) -> Result<Interpolator> {
+        if let Some(interpolator) = self.interpolator {
+            return Ok(interpolator);
+        }
+
+        Ok(self
+            .metadata
+            .get(INTERPOLATOR_KEY)
+            .map(|name| name.parse())
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Override the interpolation strategy.
+    ///
+    /// Passing `None` restores the strategy selected by the metadata.
+    pub fn set_interpolator(&mut self, interpolator: Option<Interpolator>) {
+        self.interpolator = interpolator;
+    }
+
+    /// Extrapolation strategy.
+    ///
+    /// The strategy explicitly set with [`Member::set_extrapolator`] has the precedence, otherwise
+    /// it is read from the `Extrapolator` metadata key. If neither is available, the default
+    /// [`Extrapolator::Continuation`] is used.
+    pub fn extrapolator(&self) -> Result<Extrapolator> {
+        if let Some(extrapolator) = self.extrapolator {
+            return Ok(extrapolator);
+        }
+
+        Ok(self
+            .metadata
+            .get(EXTRAPOLATOR_KEY)
+            .map(|name| name.parse())
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Override the extrapolation strategy.
+    ///
+    /// Passing `None` restores the strategy selected by the metadata.
+    pub fn set_extrapolator(&mut self, extrapolator: Option<Extrapolator>) {
+        self.extrapolator = extrapolator;
+    }
+
+    /// Interpolation and extrapolation strategies, for a member of the set with metadata `set`.
+    ///
+    /// The strategies explicitly set have the precedence, otherwise they are looked up in the
+    /// member metadata, then in the `set` ones, and finally in the global defaults, see
+    /// [`metadata::lookup`].
+    pub(crate) fn strategies(&self, set: &Mapping) -> Result<(Interpolator, Extrapolator)> {
+        let strategy = |key| -> Result<String> {
+            metadata::get(&self.metadata, set, key)?
+                .ok_or_else(|| anyhow!("No default for the metadata key '{key}'"))
+        };
+        let interpolator = match self.interpolator {
+            Some(interpolator) => interpolator,
+            None => strategy(INTERPOLATOR_KEY)?.parse()?,
+        };
+        let extrapolator = match self.extrapolator {
+            Some(extrapolator) => extrapolator,
+            None => strategy(EXTRAPOLATOR_KEY)?.parse()?,
+        };
+        Ok((interpolator, extrapolator))
+    }
+
+    /// Value of the metadata `key`, falling back to the set `info` and then to the global
+    /// defaults.
+    ///
+    /// Keys are named as in LHAPDF, see [`metadata`](crate::metadata). The `info` is converted
+    /// at each call, while [`Set::lookup`](crate::set::Set::lookup) converts it once per set.
+    pub fn lookup(&self, info: &Info, key: &str) -> Result<Option<Value>> {
+        metadata::lookup(&self.metadata, &metadata::mapping(info)?, key)
+    }
+
+    /// Typed value of the metadata `key`, see [`Member::lookup`].
+    pub fn get<T: DeserializeOwned>(&self, info: &Info, key: &str) -> Result<Option<T>> {
+        metadata::get(&self.metadata, &metadata::mapping(info)?, key)
+    }
+
+    /// Set `info`, with the values overridden by the member metadata.
+    pub fn resolve(&self, info: &Info) -> Result<Info> {
+        metadata::resolve(&self.metadata, &metadata::mapping(info)?)
+    }
+
+    /// Select the block covering the scale `mu2`.
+    ///
+    /// Blocks are sorted by increasing scale, and contiguous blocks repeat the knot on their
+    /// common boundary, usually a heavy quark threshold. A point lying exactly on a boundary is
+    /// assigned to the block above it, i.e. it is evaluated with the higher number of flavors,
+    /// as LHAPDF does.
+    /// Points below (above) the whole range are assigned to the first (last) block, where they
+    /// are extrapolated.
+    pub(crate) fn block(&self, mu2: f64) -> Result<&Block> {
+        let above = self.blocks.partition_point(|block| block.mu2grid[0] <= mu2);
+        self.blocks
+            .get(above.saturating_sub(1))
+            .ok_or_else(|| anyhow!("No block available in member"))
+    }
+
     /// Return interpolated values
+    ///
+    /// The values are computed for each `(pid, x, mu2)` tuple, and they are returned in the same
+    /// order. The block used for each point is selected according to its scale, see
+    /// [`Member::block`]. Any of the input arrays can have length one, in which case it is broadcasted
+    /// against the others, e.g. to evaluate multiple PIDs on a single point, or a single PID on
+    /// many points.
+    ///
+    /// Points outside the grids are treated according to the member
+    /// [extrapolator](Member::extrapolator). Only the strategies of the member itself are used,
+    /// while [`Set::evaluate`](crate::set::Set::evaluate) falls back on the ones of the set.
     pub fn evaluate(
         &self,
         pid: &Array1<i32>,
         x: &Array1<f64>,
         mu2: &Array1<f64>,
-        nf: &Array1<u8>,
     ) -> Result<Array1<f64>> {
-        if x.shape() != mu2.shape() || x.shape() != nf.shape() {
+        self.evaluate_with(pid, x, mu2, self.extrapolator()?)
+    }
+
+    /// Return interpolated values, with an explicit extrapolation strategy.
+    ///
+    /// Out of range points are rejected with an
+    /// [`ExtrapolationError`](crate::extrapolation::ExtrapolationError) if the strategy is
+    /// [`Extrapolator::Error`].
+    pub fn evaluate_with(
+        &self,
+        pid: &Array1<i32>,
+        x: &Array1<f64>,
+        mu2: &Array1<f64>,
+        extrapolator: Extrapolator,
+    ) -> Result<Array1<f64>> {
+        self.interpolate(pid, x, mu2, &self.interpolator()?, &extrapolator)
+    }
+
+    /// Interpolated values, with explicit strategies.
+    pub(crate) fn interpolate(
+        &self,
+        pid: &Array1<i32>,
+        x: &Array1<f64>,
+        mu2: &Array1<f64>,
+        interpolator: &Interpolator,
+        extrapolator: &Extrapolator,
+    ) -> Result<Array1<f64>> {
+        let len = [pid.len(), x.len(), mu2.len()]
+            .into_iter()
+            .max()
+            .unwrap_or_default();
+        let (Some(pid), Some(x), Some(mu2)) =
+            (pid.broadcast(len), x.broadcast(len), mu2.broadcast(len))
+        else {
             bail!("Incompatible array shapes.")
-        }
+        };
 
-        let mut values: Array1<f64> = Array1::zeros(x.raw_dim());
-        values[0] = self.blocks[(nf[0] - 3) as usize].interp(pid[0], x[0], mu2[0])?;
+        let mut values: Array1<f64> = Array1::zeros(len);
+        for (value, pid, x, mu2) in izip!(&mut values, pid, x, mu2) {
+            let block = self.block(*mu2)?;
+            *value = block.interp(*pid, *x, *mu2, interpolator, extrapolator)?;
+        }
 
         Ok(values)
     }
@@ -89,3 +376,129 @@ Member(
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::{array, Array};
+
+    /// Two blocks with a threshold at `mu2 = 25`, the upper one scaled by a factor 2.
+    fn member() -> Member {
+        let xgrid: Array1<f64> = Array::geomspace(1e-5, 1., 50).unwrap();
+        let pids = array![-1, 21, 1];
+        let block = |mu2grid: Array1<f64>, factor: f64| {
+            let values = Array3::from_shape_fn((3, 50, mu2grid.len()), |(p, i, j)| {
+                factor * (p as f64 + 1.) * xgrid[i].powf(0.5) * mu2grid[j].ln()
+            });
+            Block::new(pids.clone(), xgrid.clone(), mu2grid, values)
+        };
+
+        Member {
+            metadata: Metadata::new(),
+            blocks: vec![
+                block(array![2., 5., 10., 15., 20., 25.], 1.),
+                block(Array::geomspace(25., 1e5, 30).unwrap(), 2.),
+            ],
+            interpolator: None,
+            extrapolator: None,
+        }
+    }
+
+    #[test]
+    fn broadcast() {
+        let member = member();
+        let many_points = member
+            .evaluate(&array![21], &array![0.1, 0.2, 0.3], &array![10., 10., 100.])
+            .unwrap();
+        let many_pids = member
+            .evaluate(&array![-1, 21, 1], &array![0.1], &array![10.])
+            .unwrap();
+        assert_eq!(many_points.len(), 3);
+        assert_eq!(many_pids.len(), 3);
+        assert_eq!(many_points[0], many_pids[1]);
+        assert!((many_pids[2] / many_pids[0] - 3.).abs() < 1e-12);
+
+        assert!(member
+            .evaluate(&array![21, 1], &array![0.1, 0.2, 0.3], &array![10.])
+            .is_err());
+    }
+
+    #[test]
+    fn thresholds() {
+        let member = member();
+        let values = member
+            .evaluate(&array![21], &array![0.1], &array![24.999, 25., 25.001])
+            .unwrap();
+
+        assert!((values[1] / values[0] - 2.).abs() < 1e-3);
+        assert!((values[2] / values[1] - 1.).abs() < 1e-3);
+        // outside the whole range, extrapolate on the closest block
+        assert!(std::ptr::eq(member.block(1.).unwrap(), &member.blocks[0]));
+        assert!(std::ptr::eq(member.block(1e6).unwrap(), &member.blocks[1]));
+    }
+
+    #[test]
+    fn storage() {
+        let member = member();
+        let loaded = Member::load(member.dump().unwrap()).unwrap();
+        let legacy = bincode::serde::encode_to_vec(&member, bincode::config::standard()).unwrap();
+        let migrated = Member::load(legacy.into()).unwrap();
+        assert_eq!(migrated.blocks[0].values, member.blocks[0].values);
+        for (block, original) in loaded.blocks.iter().zip(&member.blocks) {
+            assert_eq!(block.values, original.values);
+        }
+
+        let average = Member::average(&[&member, &loaded]).unwrap();
+        assert_eq!(average.blocks[1].values, member.blocks[1].values);
+    }
+
+    #[test]
+    fn resample() {
+        let member = member();
+        let same = member.resample(&member.blocks, &Mapping::new()).unwrap();
+        for (block, original) in same.blocks.iter().zip(&member.blocks) {
+            assert_eq!(block.values, original.values);
+        }
+
+        let coarse: Vec<Block> = member
+            .blocks
+            .iter()
+            .map(|block| {
+                let xgrid = Array::geomspace(1e-4, 1., 20).unwrap();
+                let values = Array3::zeros((block.pids.len(), 20, block.mu2grid.len()));
+                Block::new(block.pids.clone(), xgrid, block.mu2grid.clone(), values)
+            })
+            .collect();
+        let resampled = member.resample(&coarse, &Mapping::new()).unwrap();
+        let values = resampled
+            .evaluate(&array![21], &array![1e-3], &array![24.999, 25.])
+            .unwrap();
+        // the threshold discontinuity is preserved
+        assert!((values[1] / values[0] - 2.).abs() < 1e-3);
+        let original = member
+            .evaluate(&array![21], &array![1e-3], &array![25.])
+            .unwrap();
+        assert!((original[0] / values[1] - 1.).abs() < 1e-3);
+
+        // a higher threshold in the target grids
+        let xgrid = member.blocks[0].xgrid.clone();
+        let target = |mu2grid: Array1<f64>| {
+            let values = Array3::zeros((3, 50, mu2grid.len()));
+            Block::new(array![-1, 21, 1], xgrid.clone(), mu2grid, values)
+        };
+        let shifted = [
+            target(array![2., 10., 20., 25., 28., 30.]),
+            target(Array::geomspace(30., 1e5, 30).unwrap()),
+        ];
+        let resampled = member.resample(&shifted, &Mapping::new()).unwrap();
+        // knots beyond the original threshold are interpolated in the upper block
+        let x = array![xgrid[20]];
+        let original = member.evaluate(&array![21], &x, &array![28.]).unwrap();
+        let value = resampled.blocks[0].values[(1, 20, 4)];
+        assert!((value / original[0] - 1.).abs() < 1e-3);
+        // while the original threshold knot keeps to the block of its target
+        let below = member.evaluate(&array![21], &x, &array![24.999]).unwrap();
+        assert!((resampled.blocks[0].values[(1, 20, 3)] / below[0] - 1.).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,148 @@
+//! Hierarchical metadata lookup
+//!
+//! As in LHAPDF, metadata keys are resolved at three levels: the member header first, then the
+//! set [`Info`], and finally the global defaults. Keys are always named as in LHAPDF, e.g.
+//! `AlphaS_MZ`, such that members can override any of the set metadata, as done by the
+//! `\alpha_s` variations of some sets.
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+
+use crate::alphas::{MASSES, MZ};
+use crate::data::lhapdf;
+use crate::extrapolation::Extrapolator;
+use crate::info::Info;
+use crate::interpolation::Interpolator;
+use crate::member::{Metadata, EXTRAPOLATOR_KEY, INTERPOLATOR_KEY};
+
+/// Global default of `key`, if any.
+fn default(key: &str) -> Option<Value> {
+    let mass = |index: usize| Some(MASSES[index].into());
+    match key {
+        INTERPOLATOR_KEY => Some(Interpolator::default().to_string().into()),
+        EXTRAPOLATOR_KEY => Some(Extrapolator::default().to_string().into()),
+        "ForcePositive" => Some(0.into()),
+        "MZ" => Some(MZ.into()),
+        "MCharm" => mass(0),
+        "MBottom" => mass(1),
+        "MTop" => mass(2),
+        _ => None,
+    }
+}
+
+/// Global defaults, the last level of the lookup.
+pub fn defaults() -> Mapping {
+    [
+        INTERPOLATOR_KEY,
+        EXTRAPOLATOR_KEY,
+        "ForcePositive",
+        "MZ",
+        "MCharm",
+        "MBottom",
+        "MTop",
+    ]
+    .into_iter()
+    .filter_map(|key| Some((key.into(), default(key)?)))
+    .collect()
+}
+
+/// Set metadata, keyed as in LHAPDF.
+///
+/// It is meant to be built once per set, and shared by all the lookups.
+pub(crate) fn mapping(info: &Info) -> Result<Mapping> {
+    lhapdf::info::mapping(info)
+}
+
+/// Parse a member metadata value, stored as text.
+fn parse(value: &str) -> Result<Value> {
+    Ok(serde_yaml::from_str(value)?)
+}
+
+/// Value of `key` in the member `metadata` only, the first level of the lookup.
+pub(crate) fn member(metadata: &Metadata, key: &str) -> Result<Option<Value>> {
+    metadata.get(key).map(|value| parse(value)).transpose()
+}
+
+/// Value of `key`, looked up in the member `metadata`, then in the `set` metadata, and finally
+/// in the global defaults.
+///
+/// The `set` metadata are the ones returned by [`mapping`].
+pub(crate) fn lookup(metadata: &Metadata, set: &Mapping, key: &str) -> Result<Option<Value>> {
+    if let Some(value) = member(metadata, key)? {
+        return Ok(Some(value));
+    }
+    Ok(set.get(key).cloned().or_else(|| default(key)))
+}
+
+/// Typed value of `key`, see [`lookup`].
+pub(crate) fn get<T: DeserializeOwned>(
+    metadata: &Metadata,
+    set: &Mapping,
+    key: &str,
+) -> Result<Option<T>> {
+    Ok(lookup(metadata, set, key)?
+        .map(serde_yaml::from_value)
+        .transpose()?)
+}
+
+/// Set metadata, with the member `metadata` overriding the `set` ones.
+///
+/// Only the keys missing from both are filled with the global defaults.
+pub(crate) fn resolve(metadata: &Metadata, set: &Mapping) -> Result<Info> {
+    let mut mapping = defaults();
+    mapping.extend(set.clone());
+    for (key, value) in metadata {
+        mapping.insert(key.as_str().into(), parse(value)?);
+    }
+
+    Ok(lhapdf::info::Info::from_mapping(mapping).try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade() {
+        let info = Info {
+            description: "A set".to_owned(),
+            alphas_mz: Some(0.118),
+            m_charm: Some(1.51),
+            more_members: [("Extrapolator".to_owned(), Value::from("nearest"))].into(),
+            ..Info::default()
+        };
+        let metadata = Metadata::from([
+            ("AlphaS_MZ".to_owned(), "0.116".to_owned()),
+            ("PdfType".to_owned(), "central".to_owned()),
+        ]);
+
+        let set = mapping(&info).unwrap();
+
+        // member, set, and global levels
+        assert_eq!(
+            get::<f64>(&metadata, &set, "AlphaS_MZ").unwrap(),
+            Some(0.116)
+        );
+        assert_eq!(get::<f64>(&metadata, &set, "MCharm").unwrap(), Some(1.51));
+        assert_eq!(get::<f64>(&metadata, &set, "MBottom").unwrap(), Some(4.19));
+        assert_eq!(
+            get::<String>(&metadata, &set, "Extrapolator").unwrap(),
+            Some("nearest".to_owned())
+        );
+        assert_eq!(
+            lookup(&metadata, &set, "Interpolator").unwrap(),
+            Some(Value::from("logcubic"))
+        );
+        assert_eq!(lookup(&metadata, &set, "Missing").unwrap(), None);
+        assert!(get::<f64>(&metadata, &set, "PdfType").is_err());
+
+        let resolved = resolve(&metadata, &set).unwrap();
+        assert_eq!(resolved.alphas_mz, Some(0.116));
+        assert_eq!((resolved.m_charm, resolved.mz), (Some(1.51), Some(MZ)));
+        assert_eq!(resolved.description, info.description);
+        assert_eq!(
+            resolved.more_members["PdfType"],
+            Value::from("central".to_owned())
+        );
+    }
+}
@@ -1 +1,170 @@
 //! A generic interpolated quantity
+use anyhow::{bail, Result};
+use ndarray::{s, Array1};
+
+use crate::interpolation::{hermite, knot_below};
+
+/// Contiguous portion of the knots, without repetitions.
+#[derive(Debug, Clone)]
+struct Subgrid {
+    knots: Array1<f64>,
+    logs: Array1<f64>,
+    values: Array1<f64>,
+}
+
+impl Subgrid {
+    fn new(knots: Array1<f64>, values: Array1<f64>) -> Self {
+        let logs = knots.mapv(f64::ln);
+        Self {
+            knots,
+            logs,
+            values,
+        }
+    }
+
+    /// Finite difference derivative in `log(knot)`, on the knot `i`.
+    fn derivative(&self, i: usize) -> f64 {
+        let (logs, values) = (&self.logs, &self.values);
+        let last = logs.len() - 1;
+        let backward = || (values[i] - values[i - 1]) / (logs[i] - logs[i - 1]);
+        let forward = || (values[i + 1] - values[i]) / (logs[i + 1] - logs[i]);
+
+        match i {
+            0 => forward(),
+            i if i == last => backward(),
+            _ => (backward() + forward()) / 2.,
+        }
+    }
+
+    /// Interpolate within the range of the subgrid.
+    fn interp(&self, at: f64) -> f64 {
+        let i = knot_below(&self.knots, at).unwrap_or(0);
+        let dlog = self.logs[i + 1] - self.logs[i];
+        let t = (at.ln() - self.logs[i]) / dlog;
+
+        hermite(
+            t,
+            self.values[i],
+            self.derivative(i) * dlog,
+            self.values[i + 1],
+            self.derivative(i + 1) * dlog,
+        )
+    }
+}
+
+/// One-dimensional interpolated quantity
+///
+/// The quantity is sampled on a sorted sequence of positive knots, which is split in subgrids
+/// wherever a knot is repeated, e.g. on flavor thresholds. Within each subgrid, it is interpolated
+/// with cubic Hermite polynomials in the logarithm of the knots, estimating the derivatives by
+/// finite differences.
+///
+/// A point lying exactly on a repeated knot is assigned to the subgrid above it.
+/// Below the first knot the quantity is extrapolated as a power law, matching the first two
+/// distinct knots, while above the last one it is frozen to its last value.
+#[derive(Debug, Clone)]
+pub struct Quantity {
+    subgrids: Vec<Subgrid>,
+}
+
+impl Quantity {
+    /// Build the quantity from its `values` on the `knots`.
+    pub fn new(knots: Array1<f64>, values: Array1<f64>) -> Result<Self> {
+        if knots.len() != values.len() {
+            bail!(
+                "Incompatible number of knots ({}) and values ({})",
+                knots.len(),
+                values.len()
+            );
+        }
+        if knots.iter().any(|k| *k <= 0.) {
+            bail!("Knots have to be positive");
+        }
+        if knots.windows(2).into_iter().any(|w| w[1] < w[0]) {
+            bail!("Knots have to be sorted");
+        }
+
+        let mut subgrids = Vec::new();
+        let mut start = 0;
+        for end in 1..=knots.len() {
+            if end == knots.len() || knots[end] == knots[end - 1] {
+                if end - start < 2 {
+                    bail!("Subgrids require at least two knots");
+                }
+                subgrids.push(Subgrid::new(
+                    knots.slice(s![start..end]).to_owned(),
+                    values.slice(s![start..end]).to_owned(),
+                ));
+                start = end;
+            }
+        }
+        if subgrids.is_empty() {
+            bail!("Quantity requires at least two knots");
+        }
+
+        Ok(Self { subgrids })
+    }
+
+    /// Smallest knot.
+    pub fn min(&self) -> f64 {
+        self.subgrids[0].knots[0]
+    }
+
+    /// Largest knot.
+    pub fn max(&self) -> f64 {
+        let last = &self.subgrids[self.subgrids.len() - 1];
+        last.knots[last.knots.len() - 1]
+    }
+
+    /// Interpolated value at `at`.
+    pub fn interp(&self, at: f64) -> f64 {
+        if at < self.min() {
+            let first = &self.subgrids[0];
+            let slope = (first.values[1] / first.values[0]).ln() / (first.logs[1] - first.logs[0]);
+            return first.values[0] * (at / first.knots[0]).powf(slope);
+        }
+        if at > self.max() {
+            let last = &self.subgrids[self.subgrids.len() - 1];
+            return last.values[last.values.len() - 1];
+        }
+
+        let above = self.subgrids.partition_point(|sub| sub.knots[0] <= at);
+        self.subgrids[above.saturating_sub(1)].interp(at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::array;
+
+    #[test]
+    fn subgrids() {
+        let knots = array![1., 2., 4., 4., 8., 16.];
+        let values = array![1., 2., 3., 30., 40., 50.];
+        let quantity = Quantity::new(knots.clone(), values.clone()).unwrap();
+
+        assert_eq!(quantity.subgrids.len(), 2);
+        for (k, v) in [(1., 1.), (2., 2.), (4., 30.), (8., 40.), (16., 50.)] {
+            assert!((quantity.interp(k) - v).abs() < 1e-12);
+        }
+        // continuity within subgrids
+        assert!((quantity.interp(4. - 1e-9) - 3.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn extrapolation() {
+        let quantity = Quantity::new(array![1., 2., 4., 8.], array![1., 2., 4., 8.]).unwrap();
+
+        assert!((quantity.interp(0.25) - 0.25).abs() < 1e-12);
+        assert_eq!(quantity.interp(100.), 8.);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Quantity::new(array![1., 2.], array![1.]).is_err());
+        assert!(Quantity::new(array![2., 1.], array![1., 1.]).is_err());
+        assert!(Quantity::new(array![1., 2., 2.], array![1., 1., 1.]).is_err());
+    }
+}
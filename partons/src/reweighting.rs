@@ -0,0 +1,186 @@
+//! Bayesian reweighting of Monte Carlo replicas
+//!
+//! The replicas of a set are assigned weights according to their `\chi^2` with respect to new
+//! data, and they can be unweighted into a new replica set, following the NNPDF procedure
+//! (arXiv:1012.0836, arXiv:1108.1758).
+use anyhow::{bail, Result};
+
+use crate::set::Set;
+use crate::uncertainty::ErrorType;
+
+/// Definition of the replica weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    /// Giele-Keller weights, `w \propto \exp(-\chi^2 / 2)`
+    GieleKeller,
+    /// NNPDF weights, `w \propto (\chi^2)^{(n - 1) / 2} \exp(-\chi^2 / 2)`, for `n` data points
+    Nnpdf {
+        /// Number of data points entering the `\chi^2`
+        points: usize,
+    },
+}
+
+/// Weights of the replicas
+///
+/// The weights are normalized to the number of replicas.
+#[derive(Debug, Clone)]
+pub struct Reweighting {
+    /// Weight of each replica, in the order of the members
+    pub weights: Vec<f64>,
+}
+
+impl Reweighting {
+    /// Compute the weights from the `\chi^2` of each replica.
+    pub fn new(chi2: &[f64], weighting: Weighting) -> Result<Self> {
+        if chi2.is_empty() {
+            bail!("No replica to reweight");
+        }
+        if chi2.iter().any(|c| !(c.is_finite() && *c > 0.)) {
+            bail!("chi2 values have to be positive");
+        }
+
+        // exponentiate only after subtracting the maximum, to avoid underflows
+        let logs: Vec<f64> = chi2
+            .iter()
+            .map(|c| match weighting {
+                Weighting::GieleKeller => -c / 2.,
+                Weighting::Nnpdf { points } => (points as f64 - 1.) / 2. * c.ln() - c / 2.,
+            })
+            .collect();
+        let max = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = logs.iter().map(|l| (l - max).exp()).collect();
+
+        let norm = chi2.len() as f64 / weights.iter().sum::<f64>();
+        Ok(Self {
+            weights: weights.into_iter().map(|w| w * norm).collect(),
+        })
+    }
+
+    /// Number of replicas.
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Whether there is no replica.
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// Effective number of replicas, from the Shannon entropy of the weights.
+    pub fn effective_replicas(&self) -> f64 {
+        let n = self.len() as f64;
+        let entropy = self
+            .weights
+            .iter()
+            .filter(|w| **w > 0.)
+            .map(|w| w * (n / w).ln())
+            .sum::<f64>()
+            / n;
+        entropy.exp()
+    }
+
+    /// Select `replicas` unweighted replicas.
+    ///
+    /// Each replica is repeated according to its weight, by the deterministic NNPDF algorithm.
+    /// The indices of the selected replicas are returned, in increasing order.
+    pub fn unweight(&self, replicas: usize) -> Vec<usize> {
+        let n = self.len() as f64;
+        let mut selected = Vec::with_capacity(replicas);
+        let mut cumulative = 0.;
+        let mut j = 1;
+
+        for (k, weight) in self.weights.iter().enumerate() {
+            let lower = cumulative;
+            cumulative += weight / n;
+            // the last replica always closes the interval, regardless of the rounding
+            let upper = if k + 1 == self.len() { 1. } else { cumulative };
+            while j <= replicas {
+                let point = j as f64 / replicas as f64;
+                if !(lower < point && point <= upper) {
+                    break;
+                }
+                selected.push(k);
+                j += 1;
+            }
+        }
+
+        selected
+    }
+}
+
+impl Set {
+    /// Compute the weights of the replicas, from their `chi2` with respect to new data.
+    ///
+    /// Only available for Monte Carlo sets, with one value for each replica, excluding the
+    /// central member.
+    pub fn reweight(&mut self, chi2: &[f64], weighting: Weighting) -> Result<Reweighting> {
+        let error_info = self.error_info()?;
+        if error_info.pdf != ErrorType::Replicas {
+            bail!("Only replicas sets can be reweighted");
+        }
+        let replicas = error_info.pdf_members().len();
+        if chi2.len() != replicas {
+            bail!("{} chi2 values given for {replicas} replicas", chi2.len());
+        }
+
+        Reweighting::new(chi2, weighting)
+    }
+
+    /// Unweight the replicas into a new set of `replicas` members, saved with the given `name`.
+    ///
+    /// The central member of the new set is the average of its replicas, while parameter
+    /// variations are not propagated.
+    pub fn unweight(
+        &mut self,
+        reweighting: &Reweighting,
+        replicas: usize,
+        name: &str,
+    ) -> Result<Set> {
+        if reweighting.len() != self.error_info()?.pdf_members().len() {
+            bail!("Weights not compatible with the set replicas");
+        }
+
+        let mut members = Vec::with_capacity(replicas);
+        for index in reweighting.unweight(replicas) {
+            members.push(self.member(u32::try_from(index + 1)?)?.clone());
+        }
+
+        let info = self.derived_info(&format!(
+            "Reweighted and unweighted to {replicas} replicas."
+        ))?;
+        self.store_replicas(name, info, members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights() {
+        let uniform = Reweighting::new(&[10.; 50], Weighting::GieleKeller).unwrap();
+        assert!(uniform.weights.iter().all(|w| (w - 1.).abs() < 1e-12));
+        assert!((uniform.effective_replicas() - 50.).abs() < 1e-10);
+
+        let gk = Reweighting::new(&[10., 12.], Weighting::GieleKeller).unwrap();
+        assert!((gk.weights[0] / gk.weights[1] - 1_f64.exp()).abs() < 1e-12);
+
+        let nnpdf = Reweighting::new(&[10., 12.], Weighting::Nnpdf { points: 11 }).unwrap();
+        let expected = (10_f64 / 12.).powi(5) * 1_f64.exp();
+        assert!((nnpdf.weights[0] / nnpdf.weights[1] - expected).abs() < 1e-12);
+
+        // far apart values, without underflows
+        let peaked = Reweighting::new(&[1e4, 2e4, 3e4], Weighting::GieleKeller).unwrap();
+        assert_eq!(peaked.weights, vec![3., 0., 0.]);
+        assert!((peaked.effective_replicas() - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unweight() {
+        let reweighting = Reweighting {
+            weights: vec![2., 0., 1., 1.],
+        };
+        assert_eq!(reweighting.unweight(4), vec![0, 0, 2, 3]);
+        assert_eq!(reweighting.unweight(8).len(), 8);
+    }
+}
@@ -0,0 +1,210 @@
+//! Sampling of the members on a grid of points
+//!
+//! Set manipulations, like compressions and conversions, are based on the values of the members
+//! on a finite set of points, spanning flavors, momentum fractions, and scales.
+use anyhow::{bail, Result};
+use ndarray::{Array, Array1, Array2};
+
+use crate::set::Set;
+
+/// Gluon PDG id.
+const GLUON: i32 = 21;
+
+/// Quark contributions to the non-singlet combinations of the evolution basis, from down to top.
+const NON_SINGLETS: [[f64; 6]; 5] = [
+    [-1., 1., 0., 0., 0., 0.],
+    [1., 1., -2., 0., 0., 0.],
+    [1., 1., 1., -3., 0., 0.],
+    [1., 1., 1., 1., -4., 0.],
+    [1., 1., 1., 1., 1., -5.],
+];
+
+/// Flavor basis of the sampled values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Basis {
+    /// The sampled flavors themselves.
+    #[default]
+    Flavor,
+    /// The evolution basis, i.e. `Σ`, `g`, `V`, `V3`, ..., `V35`, `T3`, ..., `T35`.
+    ///
+    /// Flavors not sampled are considered vanishing, and the combinations made only of them are
+    /// dropped.
+    Evolution,
+    /// Linear combinations of the sampled flavors, one per row.
+    Rotation(Array2<f64>),
+}
+
+impl Basis {
+    /// Rotation from the sampled `pids` to this basis, if any.
+    fn rotation(&self, pids: &[i32]) -> Result<Option<Array2<f64>>> {
+        match self {
+            Self::Flavor => Ok(None),
+            Self::Evolution => Ok(Some(evolution(pids))),
+            Self::Rotation(rotation) => {
+                if rotation.ncols() != pids.len() {
+                    bail!(
+                        "Rotation has {} columns, but {} flavors are sampled",
+                        rotation.ncols(),
+                        pids.len()
+                    );
+                }
+                Ok(Some(rotation.clone()))
+            }
+        }
+    }
+}
+
+/// Rotation from the flavors `pids` to the evolution basis.
+fn evolution(pids: &[i32]) -> Array2<f64> {
+    let column = |pid: i32| pids.iter().position(|p| *p == pid);
+    // quark and antiquark combination, with sign `sign` for the antiquark
+    let quarks = |coefficients: &[f64], sign: f64| {
+        let mut row = vec![0.; pids.len()];
+        for (pid, c) in (1..).zip(coefficients) {
+            for (pid, c) in [(pid, *c), (-pid, sign * c)] {
+                if let Some(col) = column(pid) {
+                    row[col] += c;
+                }
+            }
+        }
+        row
+    };
+
+    let mut gluon = vec![0.; pids.len()];
+    if let Some(col) = column(GLUON).or_else(|| column(0)) {
+        gluon[col] = 1.;
+    }
+
+    let mut rows = vec![quarks(&[1.; 6], 1.), gluon, quarks(&[1.; 6], -1.)];
+    rows.extend(NON_SINGLETS.iter().map(|c| quarks(c, -1.)));
+    rows.extend(NON_SINGLETS.iter().map(|c| quarks(c, 1.)));
+    rows.retain(|row| row.iter().any(|c| *c != 0.));
+
+    Array2::from_shape_fn((rows.len(), pids.len()), |(i, j)| rows[i][j])
+}
+
+/// Grid of points on which members are sampled
+///
+/// The points are all the combinations of the flavors in the chosen [`Basis`], the momentum
+/// fractions, and the scales.
+#[derive(Debug, Clone)]
+pub struct Sampling {
+    pids: Vec<i32>,
+    rotation: Option<Array2<f64>>,
+    x: Vec<f64>,
+    mu2: Vec<f64>,
+}
+
+impl Sampling {
+    /// Sample the flavors `pids` at the momentum fractions `x` and the scales `mu2`, in the
+    /// given `basis`.
+    pub fn new(pids: Vec<i32>, x: Vec<f64>, mu2: Vec<f64>, basis: &Basis) -> Result<Self> {
+        Ok(Self {
+            rotation: basis.rotation(&pids)?,
+            pids,
+            x,
+            mu2,
+        })
+    }
+
+    /// Default momentum fractions.
+    ///
+    /// They are logarithmically spaced in `[1e-5, 0.1]`, and linearly in `[0.1, 0.9]`, with 50
+    /// points each.
+    pub fn default_x() -> Vec<f64> {
+        let log = Array::geomspace(1e-5, 0.1, 50).unwrap_or_default();
+        let linear = Array::linspace(0.1, 0.9, 51);
+        log.iter().chain(linear.iter().skip(1)).copied().collect()
+    }
+
+    /// Flavors sampled.
+    pub fn pids(&self) -> &[i32] {
+        &self.pids
+    }
+
+    /// Momentum fractions.
+    pub fn x(&self) -> &[f64] {
+        &self.x
+    }
+
+    /// Scales.
+    pub fn mu2(&self) -> &[f64] {
+        &self.mu2
+    }
+
+    /// Number of flavors in the chosen basis.
+    fn flavors(&self) -> usize {
+        self.rotation
+            .as_ref()
+            .map_or(self.pids.len(), |rotation| rotation.nrows())
+    }
+
+    /// Number of points.
+    pub fn len(&self) -> usize {
+        self.flavors() * self.x.len() * self.mu2.len()
+    }
+
+    /// Whether there is no point.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Values of the member `num` of the `set` on all the points, see [`Set::evaluate`].
+    ///
+    /// The points are ordered by flavor in the chosen basis, then momentum fraction, and
+    /// finally scale.
+    pub fn evaluate(&self, set: &mut Set, num: u32) -> Result<Array1<f64>> {
+        let points = self.x.len() * self.mu2.len();
+        let mut pid = Vec::with_capacity(self.pids.len() * points);
+        let mut x = Vec::with_capacity(self.pids.len() * points);
+        let mut mu2 = Vec::with_capacity(self.pids.len() * points);
+        for p in &self.pids {
+            for xi in &self.x {
+                for m in &self.mu2 {
+                    pid.push(*p);
+                    x.push(*xi);
+                    mu2.push(*m);
+                }
+            }
+        }
+
+        let values = set.evaluate(
+            num,
+            &Array1::from_vec(pid),
+            &Array1::from_vec(x),
+            &Array1::from_vec(mu2),
+        )?;
+
+        Ok(match &self.rotation {
+            Some(rotation) => {
+                let values = values.into_shape((self.pids.len(), points))?;
+                Array1::from_iter(rotation.dot(&values))
+            }
+            None => values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evolution_basis() {
+        let pids = vec![-3, -2, -1, 21, 1, 2, 3];
+        let rotation = evolution(&pids);
+        assert_eq!(rotation.nrows(), 13);
+        assert_eq!(rotation.row(0).to_vec(), [1., 1., 1., 0., 1., 1., 1.]);
+        assert_eq!(rotation.row(1).to_vec(), [0., 0., 0., 1., 0., 0., 0.]);
+        assert_eq!(rotation.row(3).to_vec(), [0., -1., 1., 0., -1., 1., 0.]);
+        assert_eq!(rotation.row(9).to_vec(), [-2., 1., 1., 0., 1., 1., -2.]);
+
+        assert_eq!(evolution(&[21]).nrows(), 1);
+        assert!(Basis::Rotation(Array2::zeros((2, 3)))
+            .rotation(&pids)
+            .is_err());
+
+        let sampling = Sampling::new(pids, vec![0.1, 0.2], vec![10.], &Basis::Evolution).unwrap();
+        assert_eq!(sampling.len(), 26);
+    }
+}
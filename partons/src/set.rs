@@ -1,13 +1,20 @@
 //! Partons set
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use ndarray::Array1;
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
 
 use crate::{
-    data::{header::Header, source::Source},
+    alphas::AlphaS,
+    data::{header::Header, lhapdf, native, source::Source},
     info::Info,
-    member::Member,
+    member::{Member, PDF_TYPE_KEY},
+    metadata,
+    uncertainty::{ErrorInfo, ErrorType, Uncertainty, CL_1_SIGMA},
 };
 
 #[derive(Debug)]
@@ -16,6 +23,8 @@ pub struct Set {
     pub(crate) source: Source,
     pub(crate) header: Header,
     pub(crate) info: Option<Info>,
+    pub(crate) mapping: Option<Mapping>,
+    pub(crate) alphas: Option<AlphaS>,
     pub(crate) members: HashMap<u32, Member>,
 }
 
@@ -41,12 +50,42 @@ impl Set {
         self.info.as_ref().ok_or(anyhow!("..."))
     }
 
+    /// Metadata keyed as in LHAPDF, built once from the [`Info`], and shared by all the lookups.
+    pub(crate) fn mapping(&mut self) -> Result<&Mapping> {
+        if self.mapping.is_none() {
+            self.mapping = Some(metadata::mapping(self.info()?)?);
+        }
+
+        self.mapping
+            .as_ref()
+            .ok_or_else(|| anyhow!("Metadata not available"))
+    }
+
+    /// Strong coupling evaluator, consistent with the set.
+    ///
+    /// It only depends on the set [`Info`], ignoring any member override, e.g. of `AlphaS_MZ`
+    /// in `\alpha_s` variations. See [`Set::member_alphas`] to respect them.
+    pub fn alphas(&mut self) -> Result<&AlphaS> {
+        if self.alphas.is_none() {
+            self.alphas = Some(AlphaS::from_info(self.info()?)?);
+        };
+
+        self.alphas.as_ref().ok_or(anyhow!("..."))
+    }
+
+    /// Value of `\alpha_s` at the scale `q2`.
+    ///
+    /// See [`AlphaS::alphas_q2`].
+    pub fn alphas_q2(&mut self, q2: f64) -> Result<f64> {
+        self.alphas()?.alphas_q2(q2)
+    }
+
     /// Retrieve a set member.
+    ///
+    /// The member is returned as stored, i.e. without the set metadata. See [`Set::evaluate`] to
+    /// evaluate it with the strategies of the set.
     pub fn member(&mut self, num: u32) -> Result<&Member> {
-        if let None = self.members.get(&num) {
-            let member = self.source.member(&self.header, num)?;
-            self.members.insert(num, member);
-        }
+        self.load_member(num)?;
 
         // TODO: I have to call twice get in any case, because if I hold the reference to the first
         // returned value, I can no longer mutate self.members, and even if I return it, the borrow
@@ -54,4 +93,328 @@ impl Set {
         // since I'm developing on Rust 1.70.0, but then I don't know why...)
         Ok(&self.members.get(&num).unwrap())
     }
+
+    /// Retrieve a mutable set member.
+    ///
+    /// Useful to tune the member evaluation, e.g. with [`Member::set_interpolator`] or
+    /// [`Member::set_extrapolator`].
+    pub fn member_mut(&mut self, num: u32) -> Result<&mut Member> {
+        self.load_member(num)?;
+
+        Ok(self.members.get_mut(&num).unwrap())
+    }
+
+    /// Value of the metadata `key` for the member `num`.
+    ///
+    /// The member metadata take precedence over the set ones, which in turn take precedence
+    /// over the global defaults. See [`Member::lookup`].
+    pub fn lookup(&mut self, num: u32, key: &str) -> Result<Option<Value>> {
+        // the set metadata are loaded only when needed
+        self.load_member(num)?;
+        if let Some(value) = metadata::member(&self.members[&num].metadata, key)? {
+            return Ok(Some(value));
+        }
+
+        let (member, set) = self.member_and_mapping(num)?;
+        metadata::lookup(&member.metadata, set, key)
+    }
+
+    /// Typed value of the metadata `key` for the member `num`, see [`Set::lookup`].
+    pub fn get<T: DeserializeOwned>(&mut self, num: u32, key: &str) -> Result<Option<T>> {
+        Ok(self
+            .lookup(num, key)?
+            .map(serde_yaml::from_value)
+            .transpose()?)
+    }
+
+    /// Values of the member `num`, see [`Member::evaluate`].
+    ///
+    /// The interpolation and extrapolation strategies not chosen by the member are the ones of
+    /// the set, if any is specified in the [`Info`].
+    pub fn evaluate(
+        &mut self,
+        num: u32,
+        pid: &Array1<i32>,
+        x: &Array1<f64>,
+        mu2: &Array1<f64>,
+    ) -> Result<Array1<f64>> {
+        let (member, set) = self.member_and_mapping(num)?;
+        let (interpolator, extrapolator) = member.strategies(set)?;
+        member.interpolate(pid, x, mu2, &interpolator, &extrapolator)
+    }
+
+    /// Metadata of the member `num`, i.e. the set ones overridden by the member ones.
+    pub fn member_info(&mut self, num: u32) -> Result<Info> {
+        let (member, set) = self.member_and_mapping(num)?;
+        metadata::resolve(&member.metadata, set)
+    }
+
+    /// Strong coupling evaluator consistent with the member `num`.
+    ///
+    /// Unlike [`Set::alphas`], it respects the member overrides, e.g. of `AlphaS_MZ` in
+    /// `\alpha_s` variations.
+    pub fn member_alphas(&mut self, num: u32) -> Result<AlphaS> {
+        AlphaS::from_info(&self.member_info(num)?)
+    }
+
+    /// Number of members, including the central one.
+    pub fn size(&mut self) -> Result<u32> {
+        let size = self
+            .info()?
+            .num_members
+            .ok_or_else(|| anyhow!("Number of members not available"))?;
+        Ok(u32::try_from(size)?)
+    }
+
+    /// Kind of uncertainty represented by the members.
+    ///
+    /// It identifies the members contributing to the PDF error, and the ones corresponding to
+    /// each parameter variation.
+    pub fn error_info(&mut self) -> Result<ErrorInfo> {
+        let size = self.size()?;
+        let error_type = self
+            .info()?
+            .error_type
+            .as_deref()
+            .ok_or_else(|| anyhow!("Error type not available"))?;
+        Ok(ErrorInfo::new(error_type, size as usize)?)
+    }
+
+    /// Values of an observable on all the members, including the central one.
+    pub fn evaluate_members(
+        &mut self,
+        mut observable: impl FnMut(&Member) -> Result<f64>,
+    ) -> Result<Vec<f64>> {
+        (0..self.size()?)
+            .map(|num| observable(self.member(num)?))
+            .collect()
+    }
+
+    /// Uncertainty of an observable, from its `values` on all the members.
+    ///
+    /// The confidence level `cl` is expressed in percent, and it defaults to one standard
+    /// deviation, while the one of the set is read from the [`Info`].
+    /// See [`ErrorInfo::uncertainty`] for further details.
+    pub fn uncertainty(
+        &mut self,
+        values: &[f64],
+        cl: Option<f64>,
+        alternative: bool,
+    ) -> Result<Uncertainty> {
+        let error_info = self.error_info()?;
+        if values.len() != error_info.members {
+            bail!(
+                "{} values given for a set of {} members",
+                values.len(),
+                error_info.members
+            );
+        }
+
+        let set_cl = match error_info.pdf {
+            ErrorType::Replicas => CL_1_SIGMA,
+            _ => self.info()?.error_conf_level.unwrap_or(CL_1_SIGMA),
+        };
+        Ok(error_info.uncertainty(values, set_cl, cl.unwrap_or(CL_1_SIGMA), alternative)?)
+    }
+
+    /// Correlation coefficient between two observables, from their values `a` and `b` on all
+    /// the members.
+    ///
+    /// See [`ErrorInfo::correlation`].
+    pub fn correlation(&mut self, a: &[f64], b: &[f64]) -> Result<f64> {
+        Ok(self.error_info()?.correlation(a, b)?)
+    }
+
+    /// Correlation coefficient between two observables, evaluated on all the members.
+    pub fn observables_correlation(
+        &mut self,
+        a: impl FnMut(&Member) -> Result<f64>,
+        b: impl FnMut(&Member) -> Result<f64>,
+    ) -> Result<f64> {
+        let a = self.evaluate_members(a)?;
+        let b = self.evaluate_members(b)?;
+        self.correlation(&a, &b)
+    }
+
+    /// Save a copy of the set in the cache, with a new `name`.
+    pub fn save(&mut self, name: &str) -> Result<Set> {
+        let members = (0..self.size()?)
+            .map(|num| Ok(self.member(num)?.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        let info = self.info()?.clone();
+
+        self.source.store(name, info, &members)
+    }
+
+    /// Write the set in the LHAPDF format, within the `path` folder.
+    ///
+    /// The set is written in a subfolder named after it, which is returned.
+    pub fn export(&mut self, path: &Path) -> Result<PathBuf> {
+        lhapdf::export(self, path)
+    }
+
+    /// Write the set as a native archive, within the `path` folder.
+    ///
+    /// The archive can be hosted by a native source, and its location is returned.
+    pub fn archive(&mut self, path: &Path) -> Result<PathBuf> {
+        native::archive(self, path)
+    }
+
+    /// Metadata of a set derived from this one, with the `note` appended to the description.
+    pub(crate) fn derived_info(&mut self, note: &str) -> Result<Info> {
+        let mut info = self.info()?.clone();
+        info.description = format!("{} {note}", info.description);
+        Ok(info)
+    }
+
+    /// Store the `replicas` as a new Monte Carlo set, in the same source, with the given `name`.
+    ///
+    /// The central member is the average of the replicas, and the `info` is completed with the
+    /// error type and the number of members.
+    pub(crate) fn store_replicas(
+        &self,
+        name: &str,
+        mut info: Info,
+        replicas: Vec<Member>,
+    ) -> Result<Set> {
+        let mut members = Vec::with_capacity(replicas.len() + 1);
+        members.push(Member::average(&replicas.iter().collect::<Vec<_>>())?);
+        members.extend(replicas);
+        for (num, member) in members.iter_mut().enumerate() {
+            let pdf_type = if num == 0 { "central" } else { "replica" };
+            member
+                .metadata
+                .insert(PDF_TYPE_KEY.to_owned(), pdf_type.to_owned());
+        }
+
+        info.error_type = Some(ErrorType::Replicas.to_string());
+        info.error_conf_level = None;
+        info.num_members = Some(members.len() as u64);
+
+        self.source.store(name, info, &members)
+    }
+
+    fn member_and_mapping(&mut self, num: u32) -> Result<(&Member, &Mapping)> {
+        self.load_member(num)?;
+        self.mapping()?;
+
+        match (self.members.get(&num), self.mapping.as_ref()) {
+            (Some(member), Some(set)) => Ok((member, set)),
+            _ => bail!("Member {num} not available"),
+        }
+    }
+
+    fn load_member(&mut self, num: u32) -> Result<()> {
+        if let None = self.members.get(&num) {
+            let member = self.source.member(&self.header, num)?;
+            self.members.insert(num, member);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::array;
+
+    use crate::builder::{MemberBuilder, SetBuilder};
+    use crate::member::EXTRAPOLATOR_KEY;
+
+    #[test]
+    fn strategies() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = Source::local("local");
+        source.register_cache(dir.path().to_owned());
+
+        let member = MemberBuilder::new()
+            .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+            .build(|_, x, _| 1. - x)
+            .unwrap();
+        let info = Info {
+            description: "Toy".to_owned(),
+            more_members: [(EXTRAPOLATOR_KEY.to_owned(), Value::from("error"))].into(),
+            ..Info::default()
+        };
+        let mut set = SetBuilder::new("toy", info)
+            .member(member)
+            .build(&source)
+            .unwrap();
+
+        // the set strategy is not copied in the member
+        let (pid, x, mu2) = (array![21], array![1e-4], array![10.]);
+        let member = set.member(0).unwrap();
+        assert!(!member.metadata.contains_key(EXTRAPOLATOR_KEY));
+        assert!(member.evaluate(&pid, &x, &mu2).is_ok());
+        assert!(set.evaluate(0, &pid, &x, &mu2).is_err());
+        assert_eq!(set.evaluate(0, &pid, &array![0.1], &mu2).unwrap()[0], 0.9);
+    }
+
+    #[test]
+    fn cascade() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = Source::local("local");
+        source.register_cache(dir.path().to_owned());
+
+        let member = MemberBuilder::new()
+            .metadata("AlphaS_MZ", "0.116")
+            .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+            .build(|_, x, _| 1. - x)
+            .unwrap();
+        let info = Info {
+            description: "Toy".to_owned(),
+            alphas_mz: Some(0.118),
+            m_charm: Some(1.51),
+            ..Info::default()
+        };
+        source.store("toy", info, &[member]).unwrap();
+        let mut set = source.find("toy").unwrap();
+
+        // the set metadata are not needed for member keys
+        assert_eq!(set.get::<f64>(0, "AlphaS_MZ").unwrap(), Some(0.116));
+        assert!(set.info.is_none() && set.mapping.is_none());
+        assert_eq!(set.get::<f64>(0, "MCharm").unwrap(), Some(1.51));
+        assert_eq!(set.get::<f64>(0, "MBottom").unwrap(), Some(4.19));
+        assert_eq!(set.member_info(0).unwrap().alphas_mz, Some(0.116));
+        assert_eq!(set.info().unwrap().alphas_mz, Some(0.118));
+    }
+
+    #[test]
+    fn store_replicas() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = Source::local("local");
+        source.register_cache(dir.path().to_owned());
+
+        let member = |scale: f64| {
+            MemberBuilder::new()
+                .block(vec![21], &[1e-3, 0.1, 1.], &[2., 100.])
+                .build(|_, x, _| scale * (1. - x))
+                .unwrap()
+        };
+        let info = Info {
+            description: "Toy".to_owned(),
+            error_type: Some("hessian".to_owned()),
+            error_conf_level: Some(90.),
+            ..Info::default()
+        };
+        let mut set = SetBuilder::new("toy", info)
+            .member(member(1.))
+            .build(&source)
+            .unwrap();
+
+        let info = set.derived_info("Derived.").unwrap();
+        assert_eq!(info.description, "Toy Derived.");
+        let mut derived = set
+            .store_replicas("derived", info, vec![member(1.), member(3.)])
+            .unwrap();
+        let info = derived.info().unwrap();
+        assert_eq!(info.error_type.as_deref(), Some("replicas"));
+        assert_eq!((info.error_conf_level, info.num_members), (None, Some(3)));
+        let central = derived.member(0).unwrap();
+        assert_eq!(central.metadata[PDF_TYPE_KEY], "central");
+        assert_eq!(central.blocks[0].values[(0, 1, 0)], 2. * 0.9);
+        assert_eq!(derived.member(2).unwrap().metadata[PDF_TYPE_KEY], "replica");
+    }
 }
@@ -0,0 +1,494 @@
+//! Uncertainties over the set members
+//!
+//! The uncertainty of any observable is estimated from its values on all the members of a set,
+//! according to the set error type, reproducing the LHAPDF definitions.
+//!
+//! Compound error types, like `hessian+as+mb`, append to the PDF members a pair of members for
+//! each parameter variation, and their uncertainty is described by an [`ErrorInfo`].
+use std::f64::consts::{PI, SQRT_2};
+use std::fmt::{self, Display};
+use std::ops::Range;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// Confidence level corresponding to one standard deviation, in percent
+pub const CL_1_SIGMA: f64 = 68.268_949_213_708_58;
+
+/// Error in the uncertainty computation
+#[derive(Error, Debug, PartialEq)]
+pub enum UncertaintyError {
+    /// The error type is not recognized
+    #[error("Unknown error type '{0}'")]
+    UnknownErrorType(String),
+    /// The number of values is not compatible with the error type
+    #[error("{found} values are not compatible with a {error_type} set")]
+    InvalidValues {
+        /// Error type of the set
+        error_type: ErrorType,
+        /// Number of values provided
+        found: usize,
+    },
+    /// The parameter variation is not part of the error type
+    #[error("Unknown parameter variation '{0}'")]
+    UnknownParameter(String),
+    /// The number of members is not compatible with the error type
+    #[error("{members} members are not compatible with the error type '{error_type}'")]
+    InvalidMembers {
+        /// Full error type of the set
+        error_type: String,
+        /// Number of members, including the central one
+        members: usize,
+    },
+    /// The confidence level is not a percentage
+    #[error("Confidence level {0} outside the range (0, 100)")]
+    InvalidConfidenceLevel(f64),
+}
+
+/// Kind of uncertainty represented by the set members
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorType {
+    /// Monte Carlo replicas, the central member being their average
+    Replicas,
+    /// Asymmetric Hessian eigenvectors, as pairs of consecutive members
+    Hessian,
+    /// Symmetric Hessian eigenvectors, one member each
+    SymmHessian,
+}
+
+impl FromStr for ErrorType {
+    type Err = UncertaintyError;
+
+    /// Parse the error type name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "replicas" => Ok(Self::Replicas),
+            "hessian" => Ok(Self::Hessian),
+            "symmhessian" => Ok(Self::SymmHessian),
+            _ => Err(UncertaintyError::UnknownErrorType(s.to_owned())),
+        }
+    }
+}
+
+impl Display for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Replicas => "replicas",
+            Self::Hessian => "hessian",
+            Self::SymmHessian => "symmhessian",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Uncertainty of an observable over the set members
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uncertainty {
+    /// Central value
+    pub central: f64,
+    /// Error in the positive direction
+    pub errplus: f64,
+    /// Error in the negative direction
+    pub errminus: f64,
+    /// Symmetric error
+    pub errsymm: f64,
+    /// Factor rescaling the errors from the set confidence level to the requested one
+    pub scale: f64,
+    /// Error in the positive direction, from the PDF members only
+    pub errplus_pdf: f64,
+    /// Error in the negative direction, from the PDF members only
+    pub errminus_pdf: f64,
+    /// Symmetric error, from the PDF members only
+    pub errsymm_pdf: f64,
+    /// Symmetric error, from all the parameter variations
+    pub err_par: f64,
+    /// Symmetric error of each parameter variation, in the order of [`ErrorInfo::parameters`]
+    pub errs_par: Vec<f64>,
+}
+
+impl Uncertainty {
+    /// Uncertainty without parameter variations, the total errors being the PDF ones.
+    fn pdf((central, errplus, errminus, errsymm): (f64, f64, f64, f64), scale: f64) -> Self {
+        let (errplus, errminus, errsymm) = (scale * errplus, scale * errminus, scale * errsymm);
+        Self {
+            central,
+            errplus,
+            errminus,
+            errsymm,
+            scale,
+            errplus_pdf: errplus,
+            errminus_pdf: errminus,
+            errsymm_pdf: errsymm,
+            err_par: 0.,
+            errs_par: Vec::new(),
+        }
+    }
+}
+
+/// Error function, by its Taylor series.
+///
+/// Only accurate in the range relevant for the confidence levels, i.e. below a few units.
+fn erf(x: f64) -> f64 {
+    let x2 = x * x;
+    let (mut term, mut sum) = (x, x);
+    for n in 1..200 {
+        term *= -x2 / f64::from(n);
+        let contribution = term / f64::from(2 * n + 1);
+        sum += contribution;
+        if contribution.abs() < 1e-17 * sum.abs() {
+            break;
+        }
+    }
+    2. / PI.sqrt() * sum
+}
+
+/// Number of standard deviations corresponding to the confidence level `cl`, in percent.
+pub(crate) fn quantile(cl: f64) -> f64 {
+    // bisect the inverse of the error function
+    let target = cl / 100.;
+    let (mut low, mut high) = (0., 6.);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.;
+        if erf(mid) < target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    SQRT_2 * (low + high) / 2.
+}
+
+impl ErrorType {
+    /// Compute the uncertainty from the `values` of an observable on all the set members,
+    /// including the central one.
+    ///
+    /// The errors are rescaled from the set confidence level `set_cl` to the requested `cl`,
+    /// both in percent, assuming gaussian distributions.
+    /// With `alternative`, the replicas uncertainty is determined by the central confidence
+    /// interval of the values, instead of their standard deviation, while the Hessian symmetric
+    /// error is the average of the asymmetric ones.
+    pub fn uncertainty(
+        &self,
+        values: &[f64],
+        set_cl: f64,
+        cl: f64,
+        alternative: bool,
+    ) -> Result<Uncertainty, UncertaintyError> {
+        for level in [set_cl, cl] {
+            if !(level > 0. && level < 100.) {
+                return Err(UncertaintyError::InvalidConfidenceLevel(level));
+            }
+        }
+        let invalid = || UncertaintyError::InvalidValues {
+            error_type: *self,
+            found: values.len(),
+        };
+        let (central, members) = values.split_first().ok_or_else(invalid)?;
+
+        let scale = quantile(cl) / quantile(set_cl);
+        let errors = match self {
+            Self::Replicas => {
+                if members.len() < 2 {
+                    return Err(invalid());
+                }
+                if alternative {
+                    return Ok(interval(members, cl));
+                }
+                let n = members.len() as f64;
+                let mean = members.iter().sum::<f64>() / n;
+                let mean2 = members.iter().map(|v| v * v).sum::<f64>() / n;
+                let std = (n / (n - 1.) * (mean2 - mean * mean)).max(0.).sqrt();
+                (mean, std, std, std)
+            }
+            Self::Hessian => {
+                if members.is_empty() || members.len() % 2 != 0 {
+                    return Err(invalid());
+                }
+                let (mut errplus, mut errminus, mut errsymm) = (0., 0., 0.);
+                for pair in members.chunks(2) {
+                    let (up, down) = (pair[0] - central, pair[1] - central);
+                    errplus += up.max(down).max(0.).powi(2);
+                    errminus += (-up).max(-down).max(0.).powi(2);
+                    errsymm += (pair[0] - pair[1]).powi(2);
+                }
+                let (errplus, errminus) = (errplus.sqrt(), errminus.sqrt());
+                let errsymm = if alternative {
+                    (errplus + errminus) / 2.
+                } else {
+                    errsymm.sqrt() / 2.
+                };
+                (*central, errplus, errminus, errsymm)
+            }
+            Self::SymmHessian => {
+                if members.is_empty() {
+                    return Err(invalid());
+                }
+                let err = members
+                    .iter()
+                    .map(|v| (v - central).powi(2))
+                    .sum::<f64>()
+                    .sqrt();
+                (*central, err, err, err)
+            }
+        };
+
+        Ok(Uncertainty::pdf(errors, scale))
+    }
+}
+
+/// Full error type of a set, including parameter variations
+///
+/// The members are ordered as the central one, followed by the PDF error members, and finally a
+/// pair of members for each parameter variation, in the order in which they appear in the error
+/// type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorInfo {
+    /// Kind of the PDF uncertainty
+    pub pdf: ErrorType,
+    /// Names of the parameter variations, e.g. `as` for the strong coupling
+    pub parameters: Vec<String>,
+    /// Number of members, including the central one
+    pub members: usize,
+}
+
+impl ErrorInfo {
+    /// Parse the `error_type` of a set with the given number of `members`.
+    pub fn new(error_type: &str, members: usize) -> Result<Self, UncertaintyError> {
+        let mut parts = error_type.split('+');
+        let pdf = parts.next().unwrap_or_default().parse()?;
+        let parameters: Vec<String> = parts.map(str::to_owned).collect();
+
+        let info = Self {
+            pdf,
+            parameters,
+            members,
+        };
+        if members < 1 + 2 * info.parameters.len() {
+            return Err(UncertaintyError::InvalidMembers {
+                error_type: error_type.to_owned(),
+                members,
+            });
+        }
+        Ok(info)
+    }
+
+    /// Indices of the PDF error members, excluding the central one.
+    pub fn pdf_members(&self) -> Range<usize> {
+        1..self.members - 2 * self.parameters.len()
+    }
+
+    /// Indices of the members varying the parameter `name`.
+    pub fn parameter_members(&self, name: &str) -> Result<Range<usize>, UncertaintyError> {
+        let index = self
+            .parameters
+            .iter()
+            .position(|p| p == name)
+            .ok_or_else(|| UncertaintyError::UnknownParameter(name.to_owned()))?;
+        let start = self.pdf_members().end + 2 * index;
+        Ok(start..start + 2)
+    }
+
+    /// Compute the uncertainty from the `values` of an observable on all the set members.
+    ///
+    /// The PDF error is computed as in [`ErrorType::uncertainty`], while each parameter
+    /// contributes with the deviations of its pair of members from the central one. The total
+    /// errors are the sum in quadrature of the PDF and parameter ones, all rescaled to the
+    /// confidence level `cl`.
+    pub fn uncertainty(
+        &self,
+        values: &[f64],
+        set_cl: f64,
+        cl: f64,
+        alternative: bool,
+    ) -> Result<Uncertainty, UncertaintyError> {
+        if values.len() != self.members {
+            return Err(UncertaintyError::InvalidValues {
+                error_type: self.pdf,
+                found: values.len(),
+            });
+        }
+
+        let pdf = self.pdf_members();
+        let mut uncertainty = self
+            .pdf
+            .uncertainty(&values[..pdf.end], set_cl, cl, alternative)?;
+        if self.parameters.is_empty() {
+            return Ok(uncertainty);
+        }
+
+        let central = values[0];
+        let scale = uncertainty.scale;
+        let (mut errplus, mut errminus) = (0., 0.);
+        for pair in values[pdf.end..].chunks(2) {
+            let (up, down) = (pair[0] - central, pair[1] - central);
+            errplus += (scale * up.max(down).max(0.)).powi(2);
+            errminus += (scale * (-up).max(-down).max(0.)).powi(2);
+            uncertainty
+                .errs_par
+                .push(scale * (pair[0] - pair[1]).abs() / 2.);
+        }
+        uncertainty.err_par = uncertainty
+            .errs_par
+            .iter()
+            .map(|e| e * e)
+            .sum::<f64>()
+            .sqrt();
+
+        uncertainty.errplus = uncertainty.errplus_pdf.hypot(errplus.sqrt());
+        uncertainty.errminus = uncertainty.errminus_pdf.hypot(errminus.sqrt());
+        uncertainty.errsymm = uncertainty.errsymm_pdf.hypot(uncertainty.err_par);
+        Ok(uncertainty)
+    }
+    /// Correlation coefficient between two observables, from their values `a` and `b` on all
+    /// the set members.
+    ///
+    /// Only the PDF members contribute, with the statistics appropriate to the PDF error type.
+    pub fn correlation(&self, a: &[f64], b: &[f64]) -> Result<f64, UncertaintyError> {
+        // the confidence level is irrelevant, as long as it is not rescaled
+        let ua = self.uncertainty(a, CL_1_SIGMA, CL_1_SIGMA, false)?;
+        let ub = self.uncertainty(b, CL_1_SIGMA, CL_1_SIGMA, false)?;
+        let pdf = self.pdf_members();
+        let (ma, mb) = (&a[pdf.clone()], &b[pdf]);
+        let n = ma.len() as f64;
+
+        let covariance = match self.pdf {
+            ErrorType::Replicas => {
+                let products = ma.iter().zip(mb).map(|(a, b)| a * b).sum::<f64>();
+                (products / n - ua.central * ub.central) * n / (n - 1.)
+            }
+            ErrorType::Hessian => {
+                ma.chunks(2)
+                    .zip(mb.chunks(2))
+                    .map(|(a, b)| (a[0] - a[1]) * (b[0] - b[1]))
+                    .sum::<f64>()
+                    / 4.
+            }
+            ErrorType::SymmHessian => ma
+                .iter()
+                .zip(mb)
+                .map(|(ea, eb)| (ea - a[0]) * (eb - b[0]))
+                .sum::<f64>(),
+        };
+
+        Ok(covariance / (ua.errsymm_pdf * ub.errsymm_pdf))
+    }
+}
+
+/// Central confidence interval of the replicas `values`, at the confidence level `cl`.
+///
+/// The central value is the median of the values.
+fn interval(values: &[f64], cl: f64) -> Uncertainty {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let n = sorted.len();
+    let central = if n % 2 == 0 {
+        (sorted[n / 2 - 1] + sorted[n / 2]) / 2.
+    } else {
+        sorted[n / 2]
+    };
+    // number of values excluded on each side
+    let excluded = ((n as f64) * (1. - cl / 100.) / 2.).round() as usize;
+    let errplus = sorted[n - excluded - 1] - central;
+    let errminus = central - sorted[excluded];
+
+    Uncertainty::pdf((central, errplus, errminus, (errplus + errminus) / 2.), 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles() {
+        assert!((quantile(CL_1_SIGMA) - 1.).abs() < 1e-12);
+        assert!((quantile(95.) - 1.959_963_984_540_054).abs() < 1e-12);
+        assert!((quantile(90.) - 1.644_853_626_951_472_7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn error_types() {
+        let replicas = [2., 1., 3., 1., 3.];
+        let u = ErrorType::Replicas
+            .uncertainty(&replicas, CL_1_SIGMA, CL_1_SIGMA, false)
+            .unwrap();
+        assert_eq!(
+            (u.central, u.errsymm, u.scale),
+            (2., (4. / 3_f64).sqrt(), 1.)
+        );
+
+        let hessian = [1., 1.5, 0.8, 0.9, 1.2];
+        let u = ErrorType::Hessian
+            .uncertainty(&hessian, 90., CL_1_SIGMA, false)
+            .unwrap();
+        let scale = 1. / 1.644_853_626_951_472_7;
+        assert!((u.errplus - (0.25_f64 + 0.04).sqrt() * scale).abs() < 1e-12);
+        assert!((u.errminus - (0.04_f64 + 0.01).sqrt() * scale).abs() < 1e-12);
+        assert!((u.errsymm - (0.49_f64 + 0.09).sqrt() / 2. * scale).abs() < 1e-12);
+
+        let symmhessian = [1., 1.3, 0.6];
+        let u = ErrorType::SymmHessian
+            .uncertainty(&symmhessian, CL_1_SIGMA, CL_1_SIGMA, false)
+            .unwrap();
+        assert!((u.errsymm - 0.5).abs() < 1e-12);
+
+        assert!(ErrorType::Hessian
+            .uncertainty(&symmhessian[..2], CL_1_SIGMA, CL_1_SIGMA, false)
+            .is_err());
+        assert!("symmhessian".parse::<ErrorType>().unwrap() == ErrorType::SymmHessian);
+    }
+
+    #[test]
+    fn parameters() {
+        let info = ErrorInfo::new("hessian+as+mb", 7).unwrap();
+        assert_eq!(info.pdf_members(), 1..3);
+        assert_eq!(info.parameter_members("as").unwrap(), 3..5);
+        assert_eq!(info.parameter_members("mb").unwrap(), 5..7);
+        assert!(info.parameter_members("mc").is_err());
+        assert!(ErrorInfo::new("replicas+as", 2).is_err());
+
+        let values = [1., 1.3, 0.9, 1.1, 0.8, 1., 1.];
+        let u = info
+            .uncertainty(&values, CL_1_SIGMA, CL_1_SIGMA, false)
+            .unwrap();
+        assert!((u.errplus_pdf - 0.3).abs() < 1e-12);
+        assert!((u.errsymm_pdf - 0.2).abs() < 1e-12);
+        assert_eq!(u.errs_par.len(), 2);
+        assert!((u.errs_par[0] - 0.15).abs() < 1e-12 && u.errs_par[1] == 0.);
+        assert!((u.errplus - (0.09_f64 + 0.01).sqrt()).abs() < 1e-12);
+        assert!((u.errminus - (0.01_f64 + 0.04).sqrt()).abs() < 1e-12);
+        assert!((u.errsymm - (0.04_f64 + 0.0225).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn correlation() {
+        let info = ErrorInfo::new("replicas", 5).unwrap();
+        let a = [2.5, 1., 2., 3., 4.];
+        let linear = a.map(|v| 3. - 2. * v);
+        assert!((info.correlation(&a, &linear).unwrap() + 1.).abs() < 1e-12);
+
+        let info = ErrorInfo::new("symmhessian+as", 5).unwrap();
+        let a = [1., 2., 1., 5., 0.];
+        let b = [1., 1., 2., -7., 3.];
+        // orthogonal PDF variations, the parameters being ignored
+        assert!(info.correlation(&a, &b).unwrap().abs() < 1e-12);
+        assert!((info.correlation(&a, &a).unwrap() - 1.).abs() < 1e-12);
+
+        let info = ErrorInfo::new("hessian", 5).unwrap();
+        let a = [1., 1.2, 0.8, 1.1, 0.9];
+        let b = [1., 1.1, 0.9, 1.2, 0.8];
+        let expected = (0.4 * 0.2 + 0.2 * 0.4) / 4. / (0.2_f64.hypot(0.1) * 0.1_f64.hypot(0.2));
+        assert!((info.correlation(&a, &b).unwrap() - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn interval() {
+        let replicas: Vec<f64> = (0..=100).map(f64::from).collect();
+        let u = ErrorType::Replicas
+            .uncertainty(&replicas, CL_1_SIGMA, 90., true)
+            .unwrap();
+        // the median of the replicas 1..=100
+        assert_eq!(u.central, 50.5);
+        assert_eq!((u.errplus, u.errminus), (95. - 50.5, 50.5 - 6.));
+    }
+}